@@ -0,0 +1,64 @@
+use std::fmt;
+
+use crate::updater::normalise_version;
+
+// Which version the bootstrapper installs:
+//   stable                  whatever /version returns, installed into Versions/<version>
+//   any other channel name  /version?channel=<name>, installed into Versions/<name>-<version>
+//   a pinned version        that exact version without asking the server, installed into Versions/pinned-<version>
+
+pub const STABLE: &str = "stable";
+// Reserved for the directory prefix of pinned versions
+const PINNED: &str = "pinned";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Channel {
+    Stable,
+    Named(String),
+    Pinned(String),
+}
+
+// Channel names end up in urls and directory names
+fn validate_channel( channel: &str ) -> Result<(), String> {
+    if channel.is_empty() || !channel.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return Err(format!("Invalid update channel \"{}\", only letters, digits, '-', '_' and '.' are allowed", channel));
+    }
+    if channel.eq_ignore_ascii_case(PINNED) {
+        return Err(format!("Invalid update channel \"{}\", set updates.pinned_version to pin a version", channel));
+    }
+    Ok(())
+}
+
+impl Channel {
+    /// A pinned version takes priority over the channel
+    pub fn new( channel: &str, pinned_version: Option<&str> ) -> Result<Channel, String> {
+        if let Some(pinned_version) = pinned_version {
+            let pinned_version = normalise_version(pinned_version).map_err(|e| format!("Invalid pinned version, {}", e))?;
+            return Ok(Channel::Pinned(pinned_version));
+        }
+        validate_channel(channel)?;
+        if channel.eq_ignore_ascii_case(STABLE) {
+            return Ok(Channel::Stable);
+        }
+        Ok(Channel::Named(channel.to_ascii_lowercase()))
+    }
+
+    /// Prepended to the version to name its directory, so versions of different channels are told apart
+    pub fn directory_prefix( &self ) -> String {
+        match self {
+            Channel::Stable => String::new(),
+            Channel::Named(name) => format!("{}-", name),
+            Channel::Pinned(_) => format!("{}-", PINNED),
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+        match self {
+            Channel::Stable => write!(f, "{}", STABLE),
+            Channel::Named(name) => write!(f, "{}", name),
+            Channel::Pinned(version) => write!(f, "pinned to {}", version),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use syntax_bootstrapper::events::OutputFormat;
+use syntax_bootstrapper::http::parse_rate;
+
+// Arguments are kept simple on purpose, the bootstrapper is usually started by the browser with a single
+// syntax-player:// uri, flags are only used when it is started by hand or from a script
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Install or update, then launch the uri if one was passed
+    #[default]
+    Run,
+    /// logs [--bundle], print where the log files are or zip them for support
+    Logs { bundle: bool },
+    /// prefetch [--install-schedule | --remove-schedule], download the next version without launching it
+    Prefetch { schedule: Option<ScheduleAction> },
+    /// clean, remove partial downloads and stale versions
+    Clean,
+    /// status, summarise the installation
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleAction {
+    Install,
+    Remove,
+}
+
+#[derive(Debug, Clone)]
+pub struct CliArgs {
+    pub command: Command,
+    /// --install-dir <path>
+    pub install_dir: Option<PathBuf>,
+    /// How many times --verbose ( -v ) was passed
+    pub verbosity: u8,
+    /// --output human|json
+    pub output: OutputFormat,
+    /// --headless, no screen clearing, banner, progress bars or waiting before exit
+    pub headless: bool,
+    /// --supervise, wait for the client and report how it exited
+    pub supervise: bool,
+    /// --limit-rate <rate>, overrides network.limit_rate
+    pub limit_rate: Option<String>,
+    /// The syntax-player:// uri passed by the browser
+    pub uri: Option<String>,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        CliArgs {
+            command: Command::default(),
+            install_dir: None,
+            verbosity: 0,
+            output: OutputFormat::Human,
+            headless: false,
+            supervise: false,
+            limit_rate: None,
+            uri: None,
+        }
+    }
+}
+
+fn flag_value( name: &str, inline_value: Option<&str>, rest: &mut std::slice::Iter<String> ) -> Result<String, String> {
+    if let Some(value) = inline_value {
+        return Ok(value.to_string());
+    }
+    rest.next().cloned().ok_or_else(|| format!("Missing value for {}", name))
+}
+
+/// Parses the arguments passed to the bootstrapper, not including the executable path
+pub fn parse_args( args: &[String] ) -> Result<CliArgs, String> {
+    let mut cli_args = CliArgs::default();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if arg.starts_with("--") {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match name {
+                "--install-dir" => {
+                    cli_args.install_dir = Some(PathBuf::from(flag_value(name, inline_value, &mut rest)?));
+                },
+                "--output" => {
+                    cli_args.output = flag_value(name, inline_value, &mut rest)?.parse()?;
+                },
+                "--limit-rate" => {
+                    let limit_rate = flag_value(name, inline_value, &mut rest)?;
+                    parse_rate(&limit_rate)?;
+                    cli_args.limit_rate = Some(limit_rate);
+                },
+                "--headless" => {
+                    cli_args.headless = true;
+                },
+                "--supervise" => {
+                    cli_args.supervise = true;
+                },
+                "--verbose" => {
+                    cli_args.verbosity = cli_args.verbosity.saturating_add(1);
+                },
+                "--bundle" if matches!(cli_args.command, Command::Logs { .. }) => {
+                    cli_args.command = Command::Logs { bundle: true };
+                },
+                "--install-schedule" if cli_args.command == (Command::Prefetch { schedule: None }) => {
+                    cli_args.command = Command::Prefetch { schedule: Some(ScheduleAction::Install) };
+                },
+                "--remove-schedule" if cli_args.command == (Command::Prefetch { schedule: None }) => {
+                    cli_args.command = Command::Prefetch { schedule: Some(ScheduleAction::Remove) };
+                },
+                _ => return Err(format!("Unknown argument {}", name)),
+            }
+        } else if arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c == 'v') {
+            cli_args.verbosity = cli_args.verbosity.saturating_add((arg.len() - 1).min(u8::MAX as usize) as u8);
+        } else if arg.starts_with('-') {
+            return Err(format!("Unknown argument {}", arg));
+        } else if arg == "logs" && cli_args.command == Command::Run && cli_args.uri.is_none() {
+            cli_args.command = Command::Logs { bundle: false };
+        } else if arg == "prefetch" && cli_args.command == Command::Run && cli_args.uri.is_none() {
+            cli_args.command = Command::Prefetch { schedule: None };
+        } else if arg == "clean" && cli_args.command == Command::Run && cli_args.uri.is_none() {
+            cli_args.command = Command::Clean;
+        } else if arg == "status" && cli_args.command == Command::Run && cli_args.uri.is_none() {
+            cli_args.command = Command::Status;
+        } else if cli_args.uri.is_none() && cli_args.command == Command::Run {
+            cli_args.uri = Some(arg.clone());
+        } else {
+            return Err(format!("Unexpected argument {}", arg));
+        }
+    }
+    Ok(cli_args)
+}
@@ -0,0 +1,369 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::channel::{self, Channel};
+use crate::http;
+use crate::logging::{debug, warn, LogLevel};
+use crate::paths;
+
+// The bootstrapper reads its configuration from three places, each one overriding the previous:
+//   1. The system wide config file ( /etc/syntax/bootstrapper.toml or %ProgramData%\Syntax\bootstrapper.toml )
+//   2. The user config file inside the installation directory ( Syntax/config.toml )
+//   3. Environment variables ( SYNTAX_BASE_URL, SYNTAX_SETUP_URL, ... )
+// Anything not specified falls back to the defaults below. Unknown keys are only warned about, every bootstrapper
+// under Versions/ shares these files and an older one must still start when a newer one added a section
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub servers: ServersConfig,
+    pub directories: DirectoriesConfig,
+    pub behaviour: BehaviourConfig,
+    pub logging: LoggingConfig,
+    pub prefetch: PrefetchConfig,
+    pub updates: UpdatesConfig,
+    pub network: NetworkConfig,
+    pub cleanup: CleanupConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ServersConfig {
+    /// Host of the game website, written into AppSettings.xml and used for authentication
+    pub base_url: String,
+    /// Host serving /version, the bootstrapper and the client zips
+    pub setup_url: String,
+    /// Host used when the setup server can not be reached
+    pub fallback_setup_url: String,
+    /// Ordered list of setup hosts, replaces setup_url and fallback_setup_url when not empty.
+    /// Hosts use https unless a scheme is given ( http://localhost:8080 )
+    pub mirrors: Vec<String>,
+    /// Measure the latency of every mirror and prefer the fastest healthy one
+    pub probe_mirrors: bool,
+    /// How long the result of a probe is remembered before the mirrors are probed again
+    pub probe_interval_hours: u64,
+    /// Page opened when the bootstrapper is started without a syntax-player:// uri
+    pub landing_path: String,
+    /// Path passed to the client as the authentication url
+    pub authentication_path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct DirectoriesConfig {
+    /// Overrides the installation directory, only honoured from the system config file.
+    /// --install-dir, SYNTAX_HOME and portable mode take priority over it
+    pub install_dir: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BehaviourConfig {
+    /// Clear the terminal before printing the startup banner
+    pub clear_screen: bool,
+    /// Open the landing page in the browser when no uri is passed
+    pub open_landing_page: bool,
+    /// Wait for the client to exit and log how it went, also enabled with --supervise
+    pub supervise: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// How many launch logs, and separately prefetch logs, are kept in Syntax/Logs, the oldest are removed first
+    pub keep_files: usize,
+    /// error, warn, info, debug or trace, --verbose and SYNTAX_LOG take priority over it
+    pub level: Option<LogLevel>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CleanupConfig {
+    /// Remove partial downloads and stale versions every time the bootstrapper starts
+    pub on_startup: bool,
+    /// Complete versions kept, newest first, at least one. Prefetched versions are always kept
+    pub keep_versions: usize,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        CleanupConfig {
+            on_startup: true,
+            keep_versions: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Give up on a server that does not accept the connection within this many seconds
+    pub connect_timeout_secs: u64,
+    /// Give up on a server that stops sending data for this many seconds, 0 waits forever
+    pub read_timeout_secs: u64,
+    /// Proxy used for every request ( http:// or https:// ), HTTPS_PROXY and friends are honoured without it
+    pub proxy: Option<String>,
+    /// PEM file with an extra certificate authority to trust, for networks that intercept https
+    pub ca_certificate: Option<PathBuf>,
+    /// Download rate limit shared by every download, like 500K or 2M
+    pub limit_rate: Option<String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            proxy: None,
+            ca_certificate: None,
+            limit_rate: None,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// The download rate limit in bytes per second, if one is set
+    pub fn limit_rate_bytes( &self ) -> Result<Option<u64>, String> {
+        self.limit_rate.as_deref().map(http::parse_rate).transpose()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UpdatesConfig {
+    /// stable follows /version, any other name follows /version?channel=<name>
+    pub channel: String,
+    /// Install exactly this version instead of following the channel
+    pub pinned_version: Option<String>,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        UpdatesConfig {
+            channel: channel::STABLE.to_string(),
+            pinned_version: None,
+        }
+    }
+}
+
+impl UpdatesConfig {
+    pub fn channel( &self ) -> Result<Channel, String> {
+        Channel::new(&self.channel, self.pinned_version.as_deref())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PrefetchConfig {
+    /// How often the schedule installed by prefetch --install-schedule checks for a new version
+    pub interval_hours: u64,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        PrefetchConfig {
+            interval_hours: 6,
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            keep_files: 10,
+            level: None,
+        }
+    }
+}
+
+impl Default for ServersConfig {
+    fn default() -> Self {
+        ServersConfig {
+            base_url: "www.syntax.eco".to_string(),
+            setup_url: "setup.syntax.eco".to_string(),
+            fallback_setup_url: "d2f3pa9j0u8v6f.cloudfront.net".to_string(),
+            mirrors: Vec::new(),
+            probe_mirrors: false,
+            probe_interval_hours: 24,
+            landing_path: "/games".to_string(),
+            authentication_path: "/Login/Negotiate.ashx".to_string(),
+        }
+    }
+}
+
+impl Default for BehaviourConfig {
+    fn default() -> Self {
+        BehaviourConfig {
+            clear_screen: true,
+            open_landing_page: true,
+            supervise: false,
+        }
+    }
+}
+
+impl ServersConfig {
+    pub fn mirror_hosts(&self) -> Vec<String> {
+        if !self.mirrors.is_empty() {
+            return self.mirrors.clone();
+        }
+        vec![self.setup_url.clone(), self.fallback_setup_url.clone()]
+    }
+
+    pub fn landing_url(&self) -> String {
+        format!("https://{}/{}", self.base_url, self.landing_path.trim_start_matches('/'))
+    }
+
+    pub fn authentication_url(&self) -> String {
+        format!("https://{}/{}", self.base_url, self.authentication_path.trim_start_matches('/'))
+    }
+}
+
+pub fn system_config_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        let program_data = std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+        PathBuf::from(program_data).join("Syntax").join("bootstrapper.toml")
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        PathBuf::from("/etc/syntax/bootstrapper.toml")
+    }
+}
+
+pub fn user_config_path( installation_directory: &Path ) -> PathBuf {
+    installation_directory.join("config.toml")
+}
+
+fn read_config_file( path: &Path ) -> Result<Option<toml::Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    debug(&format!("Reading config file {}", path.to_str().unwrap_or_default().bright_blue()));
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    let value = contents.parse::<toml::Value>()
+        .map_err(|e| format!("Failed to parse config file {}: {}", path.display(), e))?;
+    Ok(Some(value))
+}
+
+// Tables are merged key by key so a user config only has to contain the values it changes
+fn merge_values( base: &mut toml::Value, overlay: toml::Value ) {
+    match (base, overlay) {
+        (toml::Value::Table(base_table), toml::Value::Table(overlay_table)) => {
+            for (key, value) in overlay_table {
+                match base_table.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => { base_table.insert(key, value); }
+                }
+            }
+        },
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn apply_env_overrides( config: &mut Config ) {
+    let overrides: [(&str, &mut String); 6] = [
+        ("SYNTAX_BASE_URL", &mut config.servers.base_url),
+        ("SYNTAX_SETUP_URL", &mut config.servers.setup_url),
+        ("SYNTAX_FALLBACK_SETUP_URL", &mut config.servers.fallback_setup_url),
+        ("SYNTAX_LANDING_PATH", &mut config.servers.landing_path),
+        ("SYNTAX_AUTHENTICATION_PATH", &mut config.servers.authentication_path),
+        ("SYNTAX_CHANNEL", &mut config.updates.channel),
+    ];
+    for (name, field) in overrides {
+        if let Ok(value) = std::env::var(name) {
+            if !value.is_empty() {
+                debug(&format!("Config override from environment: {}={}", name.bright_blue(), value.bright_blue()));
+                *field = value;
+            }
+        }
+    }
+    if let Ok(value) = std::env::var("SYNTAX_PROXY") {
+        if !value.is_empty() {
+            debug(&format!("Config override from environment: {}={}", "SYNTAX_PROXY".bright_blue(), http::redact_proxy(&value).bright_blue()));
+            config.network.proxy = Some(value);
+        }
+    }
+    if let Ok(value) = std::env::var("SYNTAX_LIMIT_RATE") {
+        if !value.is_empty() {
+            debug(&format!("Config override from environment: {}={}", "SYNTAX_LIMIT_RATE".bright_blue(), value.bright_blue()));
+            config.network.limit_rate = Some(value);
+        }
+    }
+    if let Ok(value) = std::env::var("SYNTAX_PINNED_VERSION") {
+        if !value.is_empty() {
+            debug(&format!("Config override from environment: {}={}", "SYNTAX_PINNED_VERSION".bright_blue(), value.bright_blue()));
+            config.updates.pinned_version = Some(value);
+        }
+    }
+    // SYNTAX_MIRRORS is a comma separated list of hosts
+    if let Ok(value) = std::env::var("SYNTAX_MIRRORS") {
+        if !value.is_empty() {
+            debug(&format!("Config override from environment: {}={}", "SYNTAX_MIRRORS".bright_blue(), value.bright_blue()));
+            config.servers.mirrors = value.split(',').map(|host| host.trim().to_string()).filter(|host| !host.is_empty()).collect();
+        }
+    }
+}
+
+// Keys of the config files that did not end up in the config, found by serializing it again
+fn unknown_keys( prefix: &str, read: &toml::Value, parsed: &toml::Value, unknown: &mut Vec<String> ) {
+    let toml::Value::Table(read_table) = read else {
+        return;
+    };
+    for (key, value) in read_table {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match parsed.get(key) {
+            Some(parsed_value) => unknown_keys(&path, value, parsed_value, unknown),
+            None => unknown.push(path),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the system config, then the user config from the installation directory it points at,
+    /// then applies environment variable overrides
+    pub fn load( installation_directory_override: Option<PathBuf> ) -> Result<Config, String> {
+        let mut merged = toml::Value::Table(toml::value::Table::new());
+        if let Some(system_config) = read_config_file(&system_config_path())? {
+            merge_values(&mut merged, system_config);
+        }
+
+        // Only the system config may move the installation directory, since the user config lives inside it
+        let system_only: Config = merged.clone().try_into()
+            .map_err(|e| format!("Invalid system config {}: {}", system_config_path().display(), e))?;
+        let installation_directory = match installation_directory_override.or(system_only.directories.install_dir) {
+            Some(installation_directory) => installation_directory,
+            None => paths::default_installation_directory()?,
+        };
+
+        let user_config_path = user_config_path(&installation_directory);
+        if let Some(mut user_config) = read_config_file(&user_config_path)? {
+            if let Some(table) = user_config.as_table_mut() {
+                if let Some(toml::Value::Table(directories)) = table.get_mut("directories") {
+                    directories.remove("install_dir");
+                }
+            }
+            merge_values(&mut merged, user_config);
+        }
+
+        let mut config: Config = merged.clone().try_into()
+            .map_err(|e| format!("Invalid config {}: {}", user_config_path.display(), e))?;
+        if let Ok(parsed) = toml::Value::try_from(&config) {
+            let mut unknown = Vec::new();
+            unknown_keys("", &merged, &parsed, &mut unknown);
+            for key in unknown {
+                warn(&format!("Ignoring unknown config key {}, it may belong to a newer bootstrapper", key.bright_blue()));
+            }
+        }
+        config.directories.install_dir = Some(installation_directory);
+        apply_env_overrides(&mut config);
+        config.updates.channel()?;
+        config.network.limit_rate_bytes()?;
+        Ok(config)
+    }
+
+    pub fn installation_directory( &self ) -> PathBuf {
+        self.directories.install_dir.clone().unwrap_or_default()
+    }
+}
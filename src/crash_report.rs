@@ -0,0 +1,130 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::BootstrapError;
+use crate::logging::{log_files, redact, LogKind};
+
+// After the client exits with a non-zero code everything we would ask the user for is zipped into
+// Syntax/CrashReports/crash-<timestamp>.zip:
+//   report.txt         versions, OS, Wine runner, exit code and the redacted launch arguments
+//   bootstrapper.log   the log of the run that launched the client
+//   client/...         log files the client wrote inside its Client<year> folder
+
+// Client log folders can grow large, only the newest files are kept in the report
+const MAX_CLIENT_LOGS: usize = 20;
+const MAX_CLIENT_LOG_SIZE: u64 = 8 * 1024 * 1024;
+
+pub struct CrashReport {
+    pub version: String,
+    pub client_year: String,
+    pub client_directory: PathBuf,
+    /// Already redacted
+    pub command_line: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub runtime: Duration,
+    /// Output of `wine --version`, None on Windows
+    pub runner: Option<String>,
+}
+
+fn is_client_log( path: &Path ) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| matches!(extension.to_ascii_lowercase().as_str(), "log" | "txt" | "dmp"))
+        .unwrap_or(false)
+}
+
+// Files directly in the client folder and anywhere below its logs folder, newest first
+fn client_logs( client_directory: &Path ) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    let mut directories = vec![client_directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            let in_logs_folder = path.strip_prefix(client_directory)
+                .map(|relative| relative.components().next().map(|first| first.as_os_str().eq_ignore_ascii_case("logs")).unwrap_or(false))
+                .unwrap_or(false);
+            if path.is_dir() {
+                if in_logs_folder {
+                    directories.push(path);
+                }
+            } else if (in_logs_folder || directory == client_directory) && is_client_log(&path) {
+                candidates.push(path);
+            }
+        }
+    }
+    let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    candidates.sort_by_key(|path| std::cmp::Reverse(modified(path)));
+    candidates.truncate(MAX_CLIENT_LOGS);
+    candidates
+}
+
+impl CrashReport {
+    fn summary( &self ) -> String {
+        let exit_code = self.exit_code.map(|code| code.to_string()).unwrap_or_else(|| "terminated".to_string());
+        format!(
+            "SYNTAX crash report\n\
+             Time: {}\n\
+             Bootstrapper: {}\n\
+             Client version: {}\n\
+             Client year: {}\n\
+             OS: {} {}\n\
+             Runner: {}\n\
+             Exit code: {}\n\
+             Runtime: {:.1} seconds\n\
+             Command line: {}\n",
+            chrono::Local::now().to_rfc3339(),
+            env!("CARGO_PKG_VERSION"),
+            self.version,
+            self.client_year,
+            std::env::consts::OS, std::env::consts::ARCH,
+            self.runner.as_deref().unwrap_or("native"),
+            exit_code,
+            self.runtime.as_secs_f64(),
+            self.command_line.join(" "),
+        )
+    }
+
+    /// Zips the report into the crash reports directory and returns its path
+    pub fn write( &self, crash_reports_directory: &Path, logs_directory: &Path ) -> Result<PathBuf, BootstrapError> {
+        std::fs::create_dir_all(crash_reports_directory).map_err(|e| BootstrapError::io("create", crash_reports_directory, e))?;
+        let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let report_path = crash_reports_directory.join(format!("crash-{}.zip", timestamp));
+        let report_file = std::fs::File::create(&report_path).map_err(|e| BootstrapError::io("create", &report_path, e))?;
+        let zip_error = |e: zip::result::ZipError| BootstrapError::Filesystem(format!("Failed to write {}: {}", report_path.display(), e));
+
+        let mut zip = zip::ZipWriter::new(report_file);
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        let mut add_file = |name: &str, contents: &[u8]| -> Result<(), BootstrapError> {
+            zip.start_file(name, options).map_err(zip_error)?;
+            zip.write_all(contents).map_err(|e| BootstrapError::io("write", &report_path, e))
+        };
+
+        add_file("report.txt", redact(&self.summary()).as_bytes())?;
+        // The newest log file is the one of this run
+        if let Some(log_file) = log_files(logs_directory, LogKind::Launch).last() {
+            let contents = std::fs::read_to_string(log_file).map_err(|e| BootstrapError::io("read", log_file, e))?;
+            add_file("bootstrapper.log", redact(&contents).as_bytes())?;
+        }
+        for client_log in client_logs(&self.client_directory) {
+            if std::fs::metadata(&client_log).map(|metadata| metadata.len() > MAX_CLIENT_LOG_SIZE).unwrap_or(true) {
+                continue;
+            }
+            let Ok(contents) = std::fs::read(&client_log) else {
+                continue;
+            };
+            // Text logs can carry the ticket too, minidumps are added as they are
+            let contents = match String::from_utf8(contents) {
+                Ok(text) => redact(&text).into_bytes(),
+                Err(e) => e.into_bytes(),
+            };
+            let relative = client_log.strip_prefix(&self.client_directory).unwrap_or(&client_log);
+            let name = format!("client/{}", relative.to_string_lossy().replace('\\', "/"));
+            add_file(&name, &contents)?;
+        }
+        zip.finish().map_err(zip_error)?;
+        Ok(report_path)
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+use std::path::Path;
+
+// Every failure the bootstrapper expects to run into ends up as one of these, main() prints it and exits
+// with the matching code so scripts wrapping the bootstrapper can tell what went wrong
+
+#[derive(Debug)]
+pub enum BootstrapError {
+    /// The setup server or one of its mirrors could not be reached or returned an error
+    Network(String),
+    /// A downloaded or installed file is missing or corrupted
+    Integrity(String),
+    /// Reading or writing the installation directory failed
+    Filesystem(String),
+    /// The client or the latest bootstrapper could not be started
+    Launch(String),
+    /// The configuration files could not be read
+    Config(String),
+    /// The arguments or the syntax-player:// uri could not be understood
+    Parse(String),
+}
+
+impl BootstrapError {
+    pub fn io( action: &str, path: &Path, e: std::io::Error ) -> BootstrapError {
+        BootstrapError::Filesystem(format!("Failed to {} {}: {}", action, path.display(), e))
+    }
+
+    /// Exit codes are stable, 1 is left for anything unexpected such as a panic
+    pub fn exit_code( &self ) -> i32 {
+        match self {
+            BootstrapError::Network(_) => 2,
+            BootstrapError::Integrity(_) => 3,
+            BootstrapError::Filesystem(_) => 4,
+            BootstrapError::Launch(_) => 5,
+            BootstrapError::Config(_) => 6,
+            BootstrapError::Parse(_) => 7,
+        }
+    }
+
+    /// Short name of the variant, used by --output json
+    pub fn category( &self ) -> &'static str {
+        match self {
+            BootstrapError::Network(_) => "network",
+            BootstrapError::Integrity(_) => "integrity",
+            BootstrapError::Filesystem(_) => "filesystem",
+            BootstrapError::Launch(_) => "launch",
+            BootstrapError::Config(_) => "config",
+            BootstrapError::Parse(_) => "parse",
+        }
+    }
+}
+
+impl fmt::Display for BootstrapError {
+    fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+        match self {
+            BootstrapError::Network(message) => write!(f, "Could not reach the SYNTAX servers, are you connected to the internet? ({})", message),
+            BootstrapError::Integrity(message) => write!(f, "The installation is damaged, is your antivirus removing files? ({})", message),
+            BootstrapError::Filesystem(message) => write!(f, "Could not access the installation directory, check the permissions and free disk space. ({})", message),
+            BootstrapError::Launch(message) => write!(f, "Could not start SYNTAX. ({})", message),
+            BootstrapError::Config(message) => write!(f, "The configuration file is invalid. ({})", message),
+            BootstrapError::Parse(message) => write!(f, "Could not understand the launch request, try launching again from the website. ({})", message),
+        }
+    }
+}
+
+impl std::error::Error for BootstrapError {}
+
+impl From<reqwest::Error> for BootstrapError {
+    fn from( e: reqwest::Error ) -> BootstrapError {
+        BootstrapError::Network(e.to_string())
+    }
+}
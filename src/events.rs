@@ -0,0 +1,169 @@
+use serde::Serialize;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::status::Status;
+
+// With --output json every line written to stdout is one of these events, so other launchers can follow
+// the bootstrapper without scraping the colored human output
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+// --headless is for CI and tools embedding the bootstrapper, nobody is watching the console so there is
+// no point clearing it, drawing progress bars or waiting before exiting
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str( format: &str ) -> Result<OutputFormat, String> {
+        match format {
+            "human" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown output format \"{}\", expected human or json", format)),
+        }
+    }
+}
+
+pub fn set_output_format( format: OutputFormat ) {
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+pub fn set_headless( headless: bool ) {
+    HEADLESS.store(headless, Ordering::Relaxed);
+}
+
+pub fn headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
+}
+
+/// Gives the user time to read the console before it closes, skipped in headless mode
+pub fn pause( duration: std::time::Duration ) {
+    if !headless() {
+        std::thread::sleep(duration);
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    StageStarted {
+        stage: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+    },
+    StageFinished {
+        stage: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+    },
+    DownloadProgress {
+        url: String,
+        downloaded: u64,
+        total: Option<u64>,
+        finished: bool,
+    },
+    Log {
+        level: String,
+        message: String,
+    },
+    Error {
+        code: i32,
+        category: String,
+        message: String,
+    },
+    /// The command line the client was started with, tickets are redacted
+    Launched {
+        command: Vec<String>,
+    },
+    /// Only reported when the client is supervised
+    ClientExited {
+        code: Option<i32>,
+        runtime_seconds: f64,
+        early_crash: bool,
+    },
+    CrashReport {
+        path: String,
+    },
+    /// The answer to the logs command
+    Logs {
+        directory: String,
+        latest: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bundle: Option<String>,
+    },
+    /// The answer to the status command
+    Status(Box<Status>),
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Writes the event as a single line of json, does nothing unless --output json was passed
+pub fn emit( event: Event ) {
+    if !json_output() {
+        return;
+    }
+    let envelope = Envelope { time: chrono::Local::now().to_rfc3339(), event: &event };
+    if let Ok(line) = serde_json::to_string(&envelope) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+        let _ = stdout.flush();
+    }
+}
+
+pub fn stage_started( stage: &str, version: Option<&str> ) {
+    emit(Event::StageStarted { stage: stage.to_string(), version: version.map(str::to_string) });
+}
+
+pub fn stage_finished( stage: &str, version: Option<&str> ) {
+    emit(Event::StageFinished { stage: stage.to_string(), version: version.map(str::to_string) });
+}
+
+/// Where a child process writes its stdout. In json mode stdout only carries events, so it is piped
+/// and forward_child_output turns each line into a log event
+pub fn child_stdout() -> std::process::Stdio {
+    if json_output() {
+        std::process::Stdio::piped()
+    } else {
+        std::process::Stdio::inherit()
+    }
+}
+
+/// Logs every line the child writes to a piped stdout, join the handle after waiting for the child
+pub fn forward_child_output( child: &mut std::process::Child, name: &str ) -> Option<std::thread::JoinHandle<()>> {
+    let stdout = child.stdout.take()?;
+    let name = name.to_string();
+    Some(std::thread::spawn(move || {
+        for line in std::io::BufRead::lines(std::io::BufReader::new(stdout)).map_while(Result::ok) {
+            if !line.trim().is_empty() {
+                crate::logging::info(&format!("{}: {}", name, line.trim_end()));
+            }
+        }
+    }))
+}
+
+/// Runs a helper program to completion with its stdout kept out of the event stream
+pub fn run_child( command: &mut std::process::Command, name: &str ) -> std::io::Result<std::process::ExitStatus> {
+    let mut child = command.stdout(child_stdout()).spawn()?;
+    let forwarder = forward_child_output(&mut child, name);
+    let status = child.wait();
+    if let Some(forwarder) = forwarder {
+        let _ = forwarder.join();
+    }
+    status
+}
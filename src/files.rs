@@ -0,0 +1,73 @@
+use colored::*;
+use sha1::{Digest, Sha1};
+use std::path::Path;
+
+use crate::error::BootstrapError;
+use crate::logging::{debug, info};
+
+pub async fn create_folder_if_not_exists( path: &Path ) -> Result<(), BootstrapError> {
+    if !path.exists() {
+        info(&format!("Creating folder {}", path.display().to_string().bright_blue()));
+        std::fs::create_dir_all(path).map_err(|e| BootstrapError::io("create", path, e))?;
+    }
+    Ok(())
+}
+
+pub async fn get_sha1_hash_of_file( path: &Path ) -> Result<String, BootstrapError> {
+    let mut file = std::fs::File::open(path).map_err(|e| BootstrapError::io("open", path, e))?;
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| BootstrapError::io("read", path, e))?;
+    let hash = hasher.finalize();
+    Ok(format!("{:x}", hash))
+}
+
+pub fn extract_to_dir( zip_file : &Path, target_dir : &Path ) -> Result<(), BootstrapError> {
+    info(format!("Extracting {} to {}", zip_file.display().to_string().bright_blue(), target_dir.display().to_string().bright_blue()).as_str());
+    let zip_file_cursor = std::fs::File::open(zip_file).map_err(|e| BootstrapError::io("open", zip_file, e))?;
+    zip_extract::extract(zip_file_cursor, target_dir, false)
+        .map_err(|e| BootstrapError::Integrity(format!("Failed to extract {}: {}", zip_file.display(), e)))
+}
+
+/// Size of every file in the zip once extracted, read from its central directory
+pub fn extracted_size( zip_file: &Path ) -> Result<u64, BootstrapError> {
+    let file = std::fs::File::open(zip_file).map_err(|e| BootstrapError::io("open", zip_file, e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| BootstrapError::Integrity(format!("Failed to read {}: {}", zip_file.display(), e)))?;
+    let mut size = 0;
+    for index in 0..archive.len() {
+        let entry = archive.by_index_raw(index)
+            .map_err(|e| BootstrapError::Integrity(format!("Failed to read {}: {}", zip_file.display(), e)))?;
+        size += entry.size();
+    }
+    Ok(size)
+}
+
+/// Total size of the files below path, anything that can not be read counts as empty
+pub fn directory_size( path: &Path ) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => directory_size(&entry.path()),
+            _ => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+        })
+        .sum()
+}
+
+/// Fails with a message listing what is needed when the volume holding path has less than required bytes free
+pub fn ensure_free_space( path: &Path, required: u64, purpose: &str ) -> Result<(), BootstrapError> {
+    let available = fs4::available_space(path).map_err(|e| BootstrapError::io("check the free space of", path, e))?;
+    debug(&format!(
+        "Free space on {}: {} available, {} needed to {}",
+        path.display().to_string().bright_blue(), indicatif::HumanBytes(available), indicatif::HumanBytes(required), purpose
+    ));
+    if available < required {
+        return Err(BootstrapError::Filesystem(format!(
+            "Not enough disk space to {}, {} is needed on {} but only {} is available. Free up {} and try again",
+            purpose, indicatif::HumanBytes(required), path.display(), indicatif::HumanBytes(available), indicatif::HumanBytes(required - available)
+        )));
+    }
+    Ok(())
+}
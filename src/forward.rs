@@ -0,0 +1,195 @@
+use colored::*;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::error::BootstrapError;
+use crate::logging::{debug, info, warn};
+
+// While one bootstrapper installs, a second syntax-player:// click hands its uri over instead of waiting
+// for the lock. The running bootstrapper listens on a Unix socket in the installation directory
+// ( a named pipe on Windows ), reads one uri per connection and answers "ok" once it has queued it.
+// The connection stays open until the install is done. Only a uri that is handed to the launcher is answered
+// with "launching", a sender that sees the connection close first launches the uri itself
+
+#[cfg(not(target_os = "windows"))]
+pub const SOCKET_FILENAME: &str = "bootstrapper.sock";
+
+const ACKNOWLEDGEMENT: &str = "ok";
+const LAUNCH_ACKNOWLEDGEMENT: &str = "launching";
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(target_os = "windows")]
+fn pipe_name( installation_directory: &Path ) -> String {
+    // Pipe names are global, the installation directory keeps separate installs apart
+    let hashed_directory = md5::compute(installation_directory.to_string_lossy().to_lowercase().as_bytes());
+    format!(r"\\.\pipe\syntax-bootstrapper-{:x}", hashed_directory)
+}
+
+// Queued uris with the connection each one came from, answered once the uri is handed to the launcher
+type PendingRequests = Mutex<Vec<(String, Box<dyn AsyncWrite + Unpin + Send>)>>;
+
+async fn receive_request<S: AsyncRead + AsyncWrite + Send + 'static>( stream: S, requests: &PendingRequests ) -> std::io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut uri = String::new();
+    BufReader::new(reader).read_line(&mut uri).await?;
+    let uri = uri.trim();
+    if uri.is_empty() {
+        return Ok(());
+    }
+    info("Received a launch request from another bootstrapper, it will be launched once the install is done");
+    writer.write_all(format!("{}\n", ACKNOWLEDGEMENT).as_bytes()).await?;
+    writer.flush().await?;
+    requests.lock().unwrap().push((uri.to_string(), Box::new(writer)));
+    Ok(())
+}
+
+/// Accepts launch requests from other bootstrappers until finish() is called
+pub struct ForwardListener {
+    requests: Arc<PendingRequests>,
+    task: Option<tokio::task::JoinHandle<()>>,
+    #[cfg(not(target_os = "windows"))]
+    socket_path: std::path::PathBuf,
+}
+
+impl ForwardListener {
+    /// Must only be called while holding the install lock, so nobody else is listening
+    pub fn start( installation_directory: &Path ) -> Result<ForwardListener, BootstrapError> {
+        let requests: Arc<PendingRequests> = Arc::new(Mutex::new(Vec::new()));
+        let task_requests = requests.clone();
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            let socket_path = installation_directory.join(SOCKET_FILENAME);
+            // Left behind by a bootstrapper that did not exit cleanly
+            if socket_path.exists() {
+                std::fs::remove_file(&socket_path).map_err(|e| BootstrapError::io("remove", &socket_path, e))?;
+            }
+            let listener = tokio::net::UnixListener::bind(&socket_path).map_err(|e| BootstrapError::io("listen on", &socket_path, e))?;
+            debug(&format!("Listening for launch requests on {}", socket_path.display().to_string().bright_blue()));
+            let task = tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    if let Err(e) = receive_request(stream, &task_requests).await {
+                        debug(&format!("Failed to receive a launch request: {}", e));
+                    }
+                }
+            });
+            Ok(ForwardListener { requests, task: Some(task), socket_path })
+        }
+        #[cfg(target_os = "windows")]
+        {
+            use tokio::net::windows::named_pipe::ServerOptions;
+            let pipe_name = pipe_name(installation_directory);
+            let pipe_error = |e: std::io::Error| BootstrapError::Filesystem(format!("Failed to create pipe {}: {}", pipe_name, e));
+            let mut server = ServerOptions::new().first_pipe_instance(true).create(&pipe_name).map_err(pipe_error)?;
+            debug(&format!("Listening for launch requests on {}", pipe_name.bright_blue()));
+            let task = tokio::spawn(async move {
+                loop {
+                    if server.connect().await.is_err() {
+                        break;
+                    }
+                    // A new instance has to exist before the next client connects
+                    let connected = server;
+                    server = match ServerOptions::new().create(&pipe_name) {
+                        Ok(server) => server,
+                        Err(_) => break,
+                    };
+                    if let Err(e) = receive_request(connected, &task_requests).await {
+                        debug(&format!("Failed to receive a launch request: {}", e));
+                    }
+                }
+            });
+            Ok(ForwardListener { requests, task: Some(task) })
+        }
+    }
+
+    /// Stops listening and returns the uri to launch: uri when this bootstrapper has one, otherwise the newest
+    /// forwarded one, whose sender is told it is about to be launched. Every other connection is closed without
+    /// an answer, so those senders launch their uris themselves. Dropping the listener closes all of them
+    pub async fn finish( mut self, uri: Option<&str> ) -> Option<String> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            let _ = task.await;
+        }
+        let mut requests = std::mem::take(&mut *self.requests.lock().unwrap());
+        let launched_here = if uri.is_some() { None } else { requests.pop() };
+        if !requests.is_empty() {
+            info(&format!("Handing {} launch requests back to the bootstrappers that sent them", requests.len()));
+        }
+        // Closes the connections that are not launched here
+        drop(requests);
+        if let Some(uri) = uri {
+            return Some(uri.to_string());
+        }
+        let (uri, mut writer) = launched_here?;
+        let acknowledged = writer.write_all(format!("{}\n", LAUNCH_ACKNOWLEDGEMENT).as_bytes()).await;
+        if let Err(e) = acknowledged.and(writer.flush().await) {
+            debug(&format!("Failed to tell another bootstrapper its launch request is being launched: {}", e));
+        }
+        Some(uri)
+    }
+}
+
+impl Drop for ForwardListener {
+    fn drop( &mut self ) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        #[cfg(not(target_os = "windows"))]
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+async fn read_answer<R: AsyncRead + Unpin>( reader: &mut BufReader<R>, expected: &str ) -> std::io::Result<()> {
+    let mut answer = String::new();
+    if reader.read_line(&mut answer).await? == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "the other bootstrapper closed the connection"));
+    }
+    if answer.trim() != expected {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unexpected answer \"{}\"", answer.trim())));
+    }
+    Ok(())
+}
+
+async fn send_request<S: AsyncRead + AsyncWrite>( stream: S, uri: &str ) -> std::io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let queued = async {
+        writer.write_all(format!("{}\n", uri).as_bytes()).await?;
+        writer.flush().await?;
+        read_answer(&mut reader, ACKNOWLEDGEMENT).await
+    };
+    match tokio::time::timeout(FORWARD_TIMEOUT, queued).await {
+        Ok(result) => result?,
+        Err(_) => return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "no answer")),
+    }
+
+    // The install can take minutes, there is no timeout while waiting for it
+    info("SYNTAX is being installed by another bootstrapper, it will launch the game once it is done");
+    if let Err(e) = read_answer(&mut reader, LAUNCH_ACKNOWLEDGEMENT).await {
+        warn("The other bootstrapper did not launch the game, launching it from here instead");
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Hands the uri to the bootstrapper holding the install lock and waits until it is about to launch it.
+/// Fails when none is listening or it exits before launching
+pub async fn forward_launch_request( installation_directory: &Path, uri: &str ) -> Result<(), BootstrapError> {
+    #[cfg(not(target_os = "windows"))]
+    let (target, stream) = {
+        let socket_path = installation_directory.join(SOCKET_FILENAME);
+        let stream = tokio::net::UnixStream::connect(&socket_path).await;
+        (socket_path.display().to_string(), stream)
+    };
+    #[cfg(target_os = "windows")]
+    let (target, stream) = {
+        let pipe_name = pipe_name(installation_directory);
+        let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(&pipe_name);
+        (pipe_name, stream)
+    };
+    let forward_error = |e: std::io::Error| BootstrapError::Launch(format!("Failed to forward the launch request to {}: {}", target, e));
+    let stream = stream.map_err(forward_error)?;
+    send_request(stream, uri).await.map_err(forward_error)
+}
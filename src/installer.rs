@@ -0,0 +1,426 @@
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::{CleanupConfig, Config};
+use crate::error::BootstrapError;
+use crate::events;
+use crate::files::{create_folder_if_not_exists, directory_size, ensure_free_space, extract_to_dir, extracted_size};
+use crate::forward::{forward_launch_request, ForwardListener};
+use crate::http::{download_file_prefix, HttpClient};
+use crate::lock::InstallLock;
+use crate::logging::{debug, info, warn};
+use crate::mirrors::Mirrors;
+use crate::paths::InstallationPaths;
+use crate::protocol::register_protocol;
+use crate::schedule;
+use crate::updater::{normalise_version, UpdateAction, Updater};
+
+/// Every client year shipped with a version, in the order they are downloaded
+pub const CLIENT_YEARS: [&str; 5] = ["2018", "2020", "2014", "2016", "2021"];
+
+// Written before AppSettings.xml by stage() and removed by activate()
+const STAGED_MARKER: &str = "staged";
+
+/// What collect_garbage removed
+#[derive(Debug, Default)]
+pub struct CleanupReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+}
+
+impl CleanupReport {
+    // Failing to remove something is not worth stopping for, it is tried again next time
+    fn remove( &mut self, path: &Path ) {
+        let size = if path.is_dir() {
+            directory_size(path)
+        } else {
+            std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0)
+        };
+        let removed = if path.is_dir() { std::fs::remove_dir_all(path) } else { std::fs::remove_file(path) };
+        match removed {
+            Ok(()) => {
+                debug(&format!("Removed {} ({})", path.display().to_string().bright_blue(), indicatif::HumanBytes(size)));
+                self.removed.push(path.to_path_buf());
+                self.freed_bytes += size;
+            },
+            Err(e) => warn(&format!("Failed to remove {}: {}", path.display(), e)),
+        }
+    }
+}
+
+/// What is left to do once prepare() is done
+#[derive(Debug)]
+pub enum Prepared {
+    /// The latest bootstrapper was started ( or has to be started from the website ), this one should exit,
+    /// after pausing when pause is set. The install lock is already released
+    Relaunched { pause: bool },
+    /// The uri was handed to the bootstrapper that is installing right now, it launches the game once it is done
+    Forwarded,
+    /// The version to launch and the uri to launch it with, which is this bootstrapper's own uri or, without one,
+    /// the newest uri another bootstrapper handed over while it was installed
+    Ready { version: String, uri: Option<String> },
+}
+
+pub struct Installer {
+    client: HttpClient,
+    mirrors: Arc<Mirrors>,
+    paths: InstallationPaths,
+    base_url: String,
+    cleanup: CleanupConfig,
+    prefetch_interval_hours: u64,
+}
+
+impl Installer {
+    pub fn new( client: HttpClient, mirrors: Arc<Mirrors>, paths: InstallationPaths, config: &Config ) -> Installer {
+        Installer {
+            client,
+            mirrors,
+            paths,
+            base_url: config.servers.base_url.clone(),
+            cleanup: config.cleanup.clone(),
+            prefetch_interval_hours: config.prefetch.interval_hours,
+        }
+    }
+
+    /// AppSettings.xml is written last, so a version without it is either fresh or a broken install
+    pub fn is_installed( &self, version: &str ) -> bool {
+        self.paths.app_settings_path(version).exists()
+    }
+
+    /// Downloads and extracts every client of the given version, registers the syntax-player scheme
+    /// and removes the versions the cleanup config does not keep
+    pub async fn install( &self, version: &str ) -> Result<(), BootstrapError> {
+        self.stage(version).await?;
+        self.activate(version)
+    }
+
+    /// Every complete version of the current channel, the most recently installed first
+    pub fn installed_versions( &self ) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.paths.versions_directory) else {
+            return Vec::new();
+        };
+        let mut installed_versions: Vec<(std::time::SystemTime, String)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let directory_name = entry.file_name().to_str()?.to_string();
+                let version = self.paths.version_of_directory(&directory_name)?;
+                // Skip leftovers that are not a version, or belong to another channel with a longer prefix
+                if normalise_version(version).ok()? != version || !self.is_installed(version) {
+                    return None;
+                }
+                let installed_at = std::fs::metadata(self.paths.app_settings_path(version)).and_then(|metadata| metadata.modified()).ok()?;
+                Some((installed_at, version.to_string()))
+            })
+            .collect();
+        installed_versions.sort_by_key(|(installed_at, _)| std::cmp::Reverse(*installed_at));
+        installed_versions.into_iter().map(|(_, version)| version).collect()
+    }
+
+    // Sum of the client zip sizes, zips the mirrors do not report a size for are left out
+    async fn download_size( &self, version: &str ) -> u64 {
+        let mut download_size = 0;
+        for client_year in CLIENT_YEARS {
+            let url_path = format!("{}-{}client.zip", version, client_year);
+            match self.mirrors.content_length(&self.client, &url_path).await {
+                Some(content_length) => download_size += content_length,
+                None => warn(&format!("Could not find out the size of {}, the disk space check may be too low", url_path)),
+            }
+        }
+        download_size
+    }
+
+    /// Left in a version directory by stage() until activate() makes it the current version
+    pub fn is_staged( &self, version: &str ) -> bool {
+        self.paths.version_directory(version).join(STAGED_MARKER).exists()
+    }
+
+    /// Downloads and extracts every client of the given version next to the current one, used by prefetch
+    /// so the version in use ( and a client that may be running from it ) is left alone
+    pub async fn stage( &self, version: &str ) -> Result<(), BootstrapError> {
+        let current_version_directory = self.paths.version_directory(version);
+        let latest_bootstrapper_path = self.paths.bootstrapper_path(version);
+        create_folder_if_not_exists(&current_version_directory).await?;
+        create_folder_if_not_exists(&self.paths.downloads_directory).await?;
+
+        // Checked before downloading, a full disk should fail early.
+        // The files wiped after the download make room, the bootstrapper itself stays. The extracted size is only known
+        // once the zips are downloaded, until then a version is taken to be as large as the newest installed one
+        // and never smaller than its zips. The exact size is checked again before extracting
+        let download_size = self.download_size(version).await;
+        let estimated_extracted_size = self.installed_versions().first()
+            .map(|installed_version| directory_size(&self.paths.version_directory(installed_version)))
+            .unwrap_or(0)
+            .max(download_size);
+        let reclaimed_size = directory_size(&current_version_directory)
+            .saturating_sub(std::fs::metadata(&latest_bootstrapper_path).map(|metadata| metadata.len()).unwrap_or(0));
+        let required_size = (download_size + estimated_extracted_size).saturating_sub(reclaimed_size);
+        ensure_free_space(&self.paths.root, required_size, &format!("install {}", version))?;
+
+        // Everything is downloaded before the current files are touched, a repair that loses the network
+        // keeps the version that is there
+        info("Downloading the latest client files, this may take a while.");
+        let mut client_zips = Vec::new();
+        for client_year in CLIENT_YEARS {
+            let url_path = format!("{}-{}client.zip", version, client_year);
+            let client_zip = download_file_prefix(&self.client, &self.mirrors, &url_path, &self.paths.downloads_directory).await?;
+            client_zips.push((client_year, client_zip));
+        }
+        info("Download finished, extracting files.");
+
+        let mut extracted_total = 0;
+        for (_, client_zip) in &client_zips {
+            extracted_total += extracted_size(client_zip)?;
+        }
+        let reclaimed_size = directory_size(&current_version_directory)
+            .saturating_sub(std::fs::metadata(&latest_bootstrapper_path).map(|metadata| metadata.len()).unwrap_or(0));
+        ensure_free_space(&self.paths.root, extracted_total.saturating_sub(reclaimed_size), &format!("extract {}", version))?;
+
+        // Delete every file in the current version directory except for the Bootstrapper itself
+        let entries = std::fs::read_dir(&current_version_directory).map_err(|e| BootstrapError::io("read", &current_version_directory, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| BootstrapError::io("read", &current_version_directory, e))?.path();
+            if path.is_file() {
+                if path != latest_bootstrapper_path {
+                    std::fs::remove_file(&path).map_err(|e| BootstrapError::io("remove", &path, e))?;
+                }
+            } else {
+                std::fs::remove_dir_all(&path).map_err(|e| BootstrapError::io("remove", &path, e))?;
+            }
+        }
+
+        for (client_year, client_zip) in client_zips {
+            let client_directory = current_version_directory.join(format!("Client{}", client_year));
+            create_folder_if_not_exists(&client_directory).await?;
+            extract_to_dir(&client_zip, &client_directory)?;
+        }
+
+        info("Finished extracting files, cleaning up.");
+        std::fs::remove_dir_all(&self.paths.downloads_directory).map_err(|e| BootstrapError::io("remove", &self.paths.downloads_directory, e))?;
+
+        let staged_marker_path = current_version_directory.join(STAGED_MARKER);
+        std::fs::write(&staged_marker_path, "").map_err(|e| BootstrapError::io("write", &staged_marker_path, e))?;
+
+        // Write the AppSettings.xml file
+        let app_settings_path = self.paths.app_settings_path(version);
+        let app_settings_xml = format!(
+"<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<Settings>
+	<ContentFolder>content</ContentFolder>
+	<BaseUrl>http://{}</BaseUrl>
+</Settings>", self.base_url
+        );
+        std::fs::write(&app_settings_path, app_settings_xml).map_err(|e| BootstrapError::io("write", &app_settings_path, e))?;
+        Ok(())
+    }
+
+    /// Makes a staged version the current one, registers the syntax-player scheme and removes the complete versions
+    /// the cleanup config does not keep. Half installed ones may be a prefetch at work and are left to collect_garbage
+    pub fn activate( &self, version: &str ) -> Result<(), BootstrapError> {
+        let current_version_directory = self.paths.version_directory(version);
+
+        // Install the syntax-player scheme in the registry
+        info("Installing syntax-player scheme");
+        register_protocol(&self.paths.bootstrapper_path(version))?;
+
+        let staged_marker_path = current_version_directory.join(STAGED_MARKER);
+        if staged_marker_path.exists() {
+            std::fs::remove_file(&staged_marker_path).map_err(|e| BootstrapError::io("remove", &staged_marker_path, e))?;
+        }
+
+        let mut report = CleanupReport::default();
+        for (stale_version, path) in self.stale_versions(Some(version)) {
+            if self.is_installed(&stale_version) {
+                report.remove(&path);
+            }
+        }
+        if !report.removed.is_empty() {
+            info(&format!("Removed {} old versions, freeing {}", report.removed.len(), indicatif::HumanBytes(report.freed_bytes).to_string().bright_blue()));
+        }
+        Ok(())
+    }
+
+    // Version directories of the current channel that the retention rule of collect_garbage does not keep
+    fn stale_versions( &self, version_in_use: Option<&str> ) -> Vec<(String, PathBuf)> {
+        let installed_versions = self.installed_versions();
+        let kept_versions: Vec<&String> = installed_versions.iter().filter(|version| !self.is_staged(version)).take(self.cleanup.keep_versions.max(1))
+            .chain(installed_versions.iter().filter(|version| self.is_staged(version)))
+            .collect();
+        let running_directory = std::env::current_exe().ok().and_then(|current_exe| current_exe.parent().map(Path::to_path_buf));
+
+        let Ok(entries) = std::fs::read_dir(&self.paths.versions_directory) else {
+            return Vec::new();
+        };
+        let mut stale_versions = Vec::new();
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Some(directory_name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            // Anything that is not a version of the current channel is left alone
+            let Some(version) = self.paths.version_of_directory(&directory_name) else {
+                continue;
+            };
+            if normalise_version(version).ok().as_deref() != Some(version) || !path.is_dir() {
+                continue;
+            }
+            if version_in_use == Some(version) || kept_versions.iter().any(|kept_version| kept_version.as_str() == version) {
+                continue;
+            }
+            // A freshly updated bootstrapper runs from a version that is not installed yet
+            if running_directory.as_deref().is_some_and(|running_directory| running_directory.starts_with(&path)) {
+                continue;
+            }
+            stale_versions.push((version.to_string(), path));
+        }
+        stale_versions
+    }
+
+    /// Removes what interrupted runs leave behind: partial downloads, half extracted versions and versions
+    /// that failed to be removed. The newest cleanup.keep_versions complete versions, prefetched versions, the version
+    /// about to be used and the version the bootstrapper runs from are kept. Must be called while holding the install and staging locks
+    pub fn collect_garbage( &self, version_in_use: Option<&str> ) -> CleanupReport {
+        let mut report = CleanupReport::default();
+        // Downloads/ itself is created on every start, only what is inside is left over
+        if let Ok(entries) = std::fs::read_dir(&self.paths.downloads_directory) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                report.remove(&entry.path());
+            }
+        }
+        for (_, path) in self.stale_versions(version_in_use) {
+            report.remove(&path);
+        }
+        report
+    }
+
+    // Interrupted runs leave partial downloads and versions behind, removed quietly unless there was something to remove
+    fn collect_garbage_on_startup( &self, version_in_use: &str ) {
+        if !self.cleanup.on_startup {
+            return;
+        }
+        let report = self.collect_garbage(Some(version_in_use));
+        if !report.removed.is_empty() {
+            info(&format!("Cleaned up {} stale files and folders, freeing {}", report.removed.len(), indicatif::HumanBytes(report.freed_bytes).to_string().bright_blue()));
+        }
+    }
+
+    /// Same as the cleanup on every start, but waits for a running install to finish first
+    pub async fn clean( &self ) -> Result<CleanupReport, BootstrapError> {
+        let _install_lock = InstallLock::acquire(&self.paths.root).await?;
+        let _staging_lock = InstallLock::acquire_staging(&self.paths.root).await?;
+        Ok(self.collect_garbage(None))
+    }
+
+    /// Downloads the latest version next to the current one so the next launch does not have to wait for it
+    pub async fn prefetch( &self, updater: &Updater ) -> Result<(), BootstrapError> {
+        // Only the staging lock is held while downloading, a launch meanwhile starts the installed version
+        let Some(_staging_lock) = InstallLock::try_acquire_staging(&self.paths.root)? else {
+            info("Another SYNTAX bootstrapper is installing right now, skipping the prefetch");
+            return Ok(());
+        };
+        events::stage_started("prefetch", None);
+        let latest_client_version = updater.latest_version().await?;
+        // A launch holding the install lock may be starting any version, cleaning up is left to it
+        if let Some(_install_lock) = InstallLock::try_acquire(&self.paths.root)? {
+            self.collect_garbage_on_startup(&latest_client_version);
+        }
+        if self.is_installed(&latest_client_version) {
+            info(&format!("Version {} is already installed, nothing to prefetch", latest_client_version.bright_blue()));
+        } else {
+            updater.fetch_bootstrapper(&latest_client_version).await?;
+            self.stage(&latest_client_version).await?;
+            info(&format!("Version {} is ready and will be used the next time SYNTAX is launched", latest_client_version.bright_blue()));
+        }
+        events::stage_finished("prefetch", Some(&latest_client_version));
+        Ok(())
+    }
+
+    /// Everything before a launch: makes sure the latest bootstrapper is running, then installs the latest version
+    /// or switches to a prefetched one. Only one bootstrapper does this at a time, a second one hands its uri
+    /// over to the first or waits for it. While a prefetch is downloading the installed version is used instead
+    pub async fn prepare( &self, updater: &Updater, args: &[String], uri: Option<&str> ) -> Result<Prepared, BootstrapError> {
+        let install_lock = match InstallLock::try_acquire(&self.paths.root)? {
+            Some(install_lock) => install_lock,
+            None => {
+                if let Some(uri) = uri {
+                    match forward_launch_request(&self.paths.root, uri).await {
+                        Ok(()) => {
+                            info("The other bootstrapper finished installing and is launching the game");
+                            return Ok(Prepared::Forwarded);
+                        },
+                        Err(e) => debug(&e.to_string()),
+                    }
+                }
+                InstallLock::acquire(&self.paths.root).await?
+            }
+        };
+
+        // Held by a prefetch that is downloading in the background, nothing waits for it unless there is nothing to launch
+        let mut staging_lock = InstallLock::try_acquire_staging(&self.paths.root)?;
+
+        events::stage_started("update", None);
+        let latest_client_version = match updater.latest_version().await {
+            Ok(latest_client_version) => {
+                if staging_lock.is_none() && !self.is_installed(&latest_client_version) {
+                    if let Some(installed_version) = self.installed_versions().into_iter().find(|version| !self.is_staged(version)) {
+                        info(&format!("Version {} is being downloaded in the background, launching the installed version {}", latest_client_version.bright_blue(), installed_version.bright_blue()));
+                        events::stage_finished("update", Some(&installed_version));
+                        return Ok(Prepared::Ready { version: installed_version, uri: uri.map(String::from) });
+                    }
+                    staging_lock = Some(InstallLock::acquire_staging(&self.paths.root).await?);
+                }
+                let update_action = updater.ensure_latest_bootstrapper(&latest_client_version, args).await?;
+                if let UpdateAction::Relaunched { pause } = update_action {
+                    events::stage_finished("update", Some(&latest_client_version));
+                    return Ok(Prepared::Relaunched { pause });
+                }
+                latest_client_version
+            },
+            // The setup servers being down does not mean the game servers are, so play what is already installed.
+            // A prefetched version is left for the next online launch, switching to it is not this bootstrapper's call
+            Err(e @ BootstrapError::Network(_)) => {
+                let Some(installed_version) = self.installed_versions().into_iter().find(|version| !self.is_staged(version)) else {
+                    return Err(e);
+                };
+                warn(&e.to_string());
+                warn(&format!("Launching the installed version {} offline, it may be out of date", installed_version.bright_blue()));
+                installed_version
+            },
+            Err(e) => return Err(e),
+        };
+        events::stage_finished("update", Some(&latest_client_version));
+        if staging_lock.is_some() {
+            self.collect_garbage_on_startup(&latest_client_version);
+        }
+
+        // Looks like we are running from the latest version directory, so we can continue with the update process
+        let mut uri = uri.map(String::from);
+        if !self.is_installed(&latest_client_version) {
+            events::stage_started("install", Some(&latest_client_version));
+            // Installing takes a while, other bootstrappers started meanwhile hand their uri over to us
+            let forward_listener = ForwardListener::start(&self.paths.root)
+                .map_err(|e| debug(&format!("Not accepting launch requests from other bootstrappers: {}", e)))
+                .ok();
+            self.install(&latest_client_version).await?;
+            if let Some(forward_listener) = forward_listener {
+                uri = forward_listener.finish(uri.as_deref()).await;
+            }
+            events::stage_finished("install", Some(&latest_client_version));
+            schedule::refresh(&self.paths.bootstrapper_path(&latest_client_version), &self.paths.root, self.prefetch_interval_hours);
+        } else if self.is_staged(&latest_client_version) {
+            info(&format!("Switching to prefetched version {}", latest_client_version.bright_blue()));
+            self.activate(&latest_client_version)?;
+            schedule::refresh(&self.paths.bootstrapper_path(&latest_client_version), &self.paths.root, self.prefetch_interval_hours);
+        }
+        drop(staging_lock);
+        drop(install_lock);
+        Ok(Prepared::Ready { version: latest_client_version, uri })
+    }
+
+    /// Installs the version again, for a client that crashed right after starting
+    pub async fn repair( &self, version: &str ) -> Result<(), BootstrapError> {
+        let _install_lock = InstallLock::acquire(&self.paths.root).await?;
+        let _staging_lock = InstallLock::acquire_staging(&self.paths.root).await?;
+        self.install(version).await
+    }
+}
@@ -0,0 +1,248 @@
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::crash_report::CrashReport;
+use crate::error::BootstrapError;
+use crate::events::{self, Event};
+use crate::logging::{debug, info, redact, register_secret, warn};
+use crate::paths::InstallationPaths;
+use crate::uri::LaunchRequest;
+
+// A client that fails within this long after starting most likely never got past loading its files
+const EARLY_CRASH_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// What happened to the client after it was started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchOutcome {
+    /// The bootstrapper did not supervise the client
+    Detached,
+    /// The client exited while supervised, code is None when it was killed by a signal
+    Exited { code: Option<i32>, runtime: Duration },
+}
+
+impl LaunchOutcome {
+    /// The client failed shortly after starting, which usually means a damaged installation
+    pub fn is_early_crash( &self ) -> bool {
+        match self {
+            LaunchOutcome::Detached => false,
+            LaunchOutcome::Exited { code, runtime } => *code != Some(0) && *runtime < EARLY_CRASH_THRESHOLD,
+        }
+    }
+}
+
+fn log_outcome( outcome: &LaunchOutcome ) {
+    let LaunchOutcome::Exited { code, runtime } = *outcome else {
+        return;
+    };
+    let exit = match code {
+        Some(code) => format!("exited with code {}", code),
+        None => "was terminated".to_string(),
+    };
+    let message = format!("SYNTAX {} after running for {:.1} seconds", exit, runtime.as_secs_f64());
+    if outcome.is_early_crash() {
+        warn(&message);
+    } else {
+        info(&message);
+    }
+    events::emit(Event::ClientExited { code, runtime_seconds: runtime.as_secs_f64(), early_crash: outcome.is_early_crash() });
+}
+
+pub struct Launcher {
+    version: String,
+    version_directory: PathBuf,
+    logs_directory: PathBuf,
+    crash_reports_directory: PathBuf,
+    app_settings_path: PathBuf,
+    authentication_url: String,
+    supervise: bool,
+    #[cfg(not(target_os = "windows"))]
+    wine: String,
+}
+
+/// Opens the website, used when the bootstrapper is started without a syntax-player:// uri
+pub fn open_landing_page( url: &str ) -> Result<(), BootstrapError> {
+    #[cfg(target_os = "windows")]
+    let open_command = events::run_child(std::process::Command::new("cmd").arg("/c").arg("start").arg(url), "start");
+    #[cfg(not(target_os = "windows"))]
+    let open_command = events::run_child(std::process::Command::new("xdg-open").arg(url), "xdg-open");
+    open_command.map_err(|e| BootstrapError::Launch(format!("Failed to open {}: {}", url, e)))?;
+    Ok(())
+}
+
+// Reported to --output json once the client is running, the ticket is redacted like in the logs
+fn emit_launched( command: &std::process::Command ) {
+    events::emit(Event::Launched { command: redacted_command_line(command) });
+}
+
+fn redacted_command_line( command: &std::process::Command ) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| redact(&arg.to_string_lossy()))
+        .collect()
+}
+
+// We allow user to specify the wine binary path in installation_directory/winepath.txt
+#[cfg(not(target_os = "windows"))]
+const WINE_PATH_FILENAME: &str = "winepath.txt";
+
+/// The wine binary from installation_directory/winepath.txt, None means wine from the PATH is used
+#[cfg(not(target_os = "windows"))]
+pub fn custom_wine_binary( installation_directory: &Path ) -> Result<Option<String>, BootstrapError> {
+    let wine_path_file = installation_directory.join(WINE_PATH_FILENAME);
+    if !wine_path_file.exists() {
+        return Ok(None);
+    }
+    let wine = std::fs::read_to_string(&wine_path_file).map_err(|e| BootstrapError::io("read", &wine_path_file, e))?;
+    Ok(Some(wine.trim().to_string()))
+}
+
+/// What `<wine> --version` prints, None when it can not be run
+#[cfg(not(target_os = "windows"))]
+pub fn wine_version( wine: &str ) -> Option<String> {
+    let output = std::process::Command::new(wine).arg("--version").output().ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+impl Launcher {
+    pub fn new( config: &Config, paths: &InstallationPaths, version: &str ) -> Result<Launcher, BootstrapError> {
+        #[cfg(not(target_os = "windows"))]
+        let wine = match custom_wine_binary(&paths.root)? {
+            Some(wine) => {
+                info(&format!("Using custom wine binary: {}", wine.bright_blue()));
+                wine
+            },
+            None => {
+                info("No custom wine binary specified, using default wine command");
+                info(format!("If you want to use a custom wine binary, please create a file at {} with the path to the wine binary", paths.root.join(WINE_PATH_FILENAME).display()).as_str());
+                "wine".to_string()
+            },
+        };
+        Ok(Launcher {
+            version: version.to_string(),
+            version_directory: paths.version_directory(version),
+            logs_directory: paths.logs_directory.clone(),
+            crash_reports_directory: paths.crash_reports_directory.clone(),
+            app_settings_path: paths.app_settings_path(version),
+            authentication_url: config.servers.authentication_url(),
+            supervise: config.behaviour.supervise,
+            #[cfg(not(target_os = "windows"))]
+            wine,
+        })
+    }
+
+    /// Unknown client years fall back to the 2016 client
+    pub fn client_executable_path( &self, client_year: &str ) -> PathBuf {
+        let client_directory = match client_year {
+            "2018" | "2020" | "2014" | "2021" => format!("Client{}", client_year),
+            _ => "Client2016".to_string(),
+        };
+        self.version_directory.join(client_directory).join("SyntaxPlayerBeta.exe")
+    }
+
+    // Shown in crash reports, the version of the wine build matters more than anything else on Linux
+    fn runner_version( &self ) -> Option<String> {
+        #[cfg(target_os = "windows")]
+        {
+            None
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            Some(format!("{} {}", self.wine, wine_version(&self.wine).unwrap_or_default()).trim().to_string())
+        }
+    }
+
+    // A crash report that can not be written is not worth failing the launch over
+    fn write_crash_report( &self, request: &LaunchRequest, client_executable_path: &Path, command: &std::process::Command, outcome: &LaunchOutcome ) {
+        let LaunchOutcome::Exited { code, runtime } = *outcome else {
+            return;
+        };
+        let crash_report = CrashReport {
+            version: self.version.clone(),
+            client_year: request.client_year.clone(),
+            client_directory: client_executable_path.parent().unwrap_or(&self.version_directory).to_path_buf(),
+            command_line: redacted_command_line(command),
+            exit_code: code,
+            runtime,
+            runner: self.runner_version(),
+        };
+        match crash_report.write(&self.crash_reports_directory, &self.logs_directory) {
+            Ok(report_path) => {
+                info(&format!("Crash report saved to {}, attach this file to your support ticket", report_path.display().to_string().bright_blue()));
+                events::emit(Event::CrashReport { path: report_path.display().to_string() });
+            },
+            Err(e) => warn(&format!("Failed to write a crash report: {}", e)),
+        }
+    }
+
+    pub fn launch( &self, request: &LaunchRequest ) -> Result<LaunchOutcome, BootstrapError> {
+        debug(&request.client_year);
+        let client_executable_path = self.client_executable_path(&request.client_year);
+        if !client_executable_path.exists() {
+            // Delete AppSettings.xml so the bootstrapper will download the client again
+            std::fs::remove_file(&self.app_settings_path).map_err(|e| BootstrapError::io("remove", &self.app_settings_path, e))?;
+            return Err(BootstrapError::Integrity(format!("{} is missing, the bootstrapper will attempt to redownload the client on next launch", client_executable_path.display())));
+        }
+        match request.launch_mode.as_str() {
+            "play" => {
+                info("Launching SYNTAX");
+                let launch_error = |e: std::io::Error| BootstrapError::Launch(format!("Failed to run {}: {}", client_executable_path.display(), e));
+                let client_args = ["--play", "--authenticationUrl", self.authentication_url.as_str(), "--authenticationTicket", request.authentication_ticket.as_str(), "--joinScriptUrl", request.join_script.as_str()];
+                register_secret(&request.authentication_ticket);
+                #[cfg(target_os = "windows")]
+                let mut command = std::process::Command::new(&client_executable_path);
+                #[cfg(not(target_os = "windows"))]
+                let mut command = {
+                    // We have to launch the game through wine
+                    let mut command = std::process::Command::new(&self.wine);
+                    command.arg(&client_executable_path);
+                    command
+                };
+                command.args(client_args);
+                command.stdout(events::child_stdout());
+                // Nobody is left to read a pipe once the bootstrapper exits without waiting for the client
+                #[cfg(target_os = "windows")]
+                if events::json_output() && !self.supervise {
+                    command.stdout(std::process::Stdio::null());
+                }
+                let started = Instant::now();
+                let mut child = command.spawn().map_err(launch_error)?;
+                let client_output = events::forward_child_output(&mut child, "client");
+                emit_launched(&command);
+
+                if self.supervise {
+                    let status = child.wait().map_err(launch_error)?;
+                    if let Some(client_output) = client_output {
+                        let _ = client_output.join();
+                    }
+                    let outcome = LaunchOutcome::Exited { code: status.code(), runtime: started.elapsed() };
+                    log_outcome(&outcome);
+                    if !status.success() {
+                        self.write_crash_report(request, &client_executable_path, &command, &outcome);
+                    }
+                    return Ok(outcome);
+                }
+                #[cfg(target_os = "windows")]
+                events::pause(std::time::Duration::from_secs(5));
+                #[cfg(not(target_os = "windows"))]
+                {
+                    // We must wait for the game to exit before exiting the bootstrapper, so a crash can be reported anyway
+                    let status = child.wait().map_err(launch_error)?;
+                    if let Some(client_output) = client_output {
+                        let _ = client_output.join();
+                    }
+                    if !status.success() {
+                        let outcome = LaunchOutcome::Exited { code: status.code(), runtime: started.elapsed() };
+                        log_outcome(&outcome);
+                        self.write_crash_report(request, &client_executable_path, &command, &outcome);
+                    }
+                    events::pause(std::time::Duration::from_secs(1));
+                }
+                Ok(LaunchOutcome::Detached)
+            },
+            _ => Err(BootstrapError::Parse(format!("Unknown launch mode \"{}\"", request.launch_mode))),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Install, update and launch logic of the SYNTAX bootstrapper
+//!
+//! The binary is a thin wrapper around these types, other tools can use them to embed the same behaviour:
+//! - [`Updater`] finds the latest client version and makes sure the latest bootstrapper is running
+//! - [`Installer`] downloads and extracts the clients of a version, and runs the install, prefetch and cleanup steps around it
+//! - [`UriParser`] turns a `syntax-player://` uri into a [`LaunchRequest`]
+//! - [`Launcher`] starts the client for a [`LaunchRequest`]
+
+pub mod channel;
+pub mod config;
+pub mod crash_report;
+pub mod error;
+pub mod events;
+pub mod files;
+pub mod forward;
+pub mod http;
+pub mod installer;
+pub mod launcher;
+pub mod lock;
+pub mod logging;
+pub mod mirrors;
+pub mod paths;
+pub mod protocol;
+pub mod schedule;
+pub mod status;
+pub mod updater;
+pub mod uri;
+
+pub use channel::Channel;
+pub use config::Config;
+pub use error::BootstrapError;
+pub use installer::Installer;
+pub use launcher::{LaunchOutcome, Launcher};
+pub use mirrors::Mirrors;
+pub use paths::InstallationPaths;
+pub use updater::{UpdateAction, Updater};
+pub use uri::{LaunchRequest, UriParser};
@@ -0,0 +1,110 @@
+use colored::*;
+use std::fs::{File, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::error::BootstrapError;
+use crate::logging::{debug, info};
+
+// Clicking play twice starts two bootstrappers, without this they would both wipe and extract into the
+// same Versions/<version> folder. The lock is advisory and released by the OS when the process exits,
+// so a crashed bootstrapper never leaves the installation locked.
+// Downloading into Downloads/ or a version that is not installed yet takes the staging lock instead. A prefetch
+// only holds that one, so a launch holding the install lock can still start the installed version meanwhile.
+// Whoever needs both takes the install lock first, and only prefetch takes the staging lock first, without waiting
+
+pub const LOCK_FILENAME: &str = "install.lock";
+pub const STAGING_LOCK_FILENAME: &str = "staging.lock";
+
+// Long enough for a slow first install, short enough that a hung bootstrapper does not block forever
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Held while installing, updating or staging, dropping it releases the lock
+#[derive(Debug)]
+pub struct InstallLock {
+    _file: File,
+    path: PathBuf,
+}
+
+fn open_lock_file( path: &Path ) -> Result<File, BootstrapError> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| BootstrapError::io("open", path, e))
+}
+
+impl InstallLock {
+    fn locked( mut file: File, path: PathBuf ) -> InstallLock {
+        // The pid is only there to help whoever looks at a stuck lock file
+        let _ = file.set_len(0);
+        let _ = writeln!(file, "{}", std::process::id());
+        debug(&format!("Acquired install lock {}", path.display().to_string().bright_blue()));
+        InstallLock { _file: file, path }
+    }
+
+    fn try_acquire_file( path: PathBuf ) -> Result<Option<InstallLock>, BootstrapError> {
+        let file = open_lock_file(&path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(InstallLock::locked(file, path))),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(BootstrapError::io("lock", &path, e)),
+        }
+    }
+
+    async fn acquire_file( path: PathBuf, waiting_message: &str ) -> Result<InstallLock, BootstrapError> {
+        let file = open_lock_file(&path)?;
+
+        let started = Instant::now();
+        let mut waiting = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {
+                    if !waiting {
+                        info(waiting_message);
+                        waiting = true;
+                    }
+                    if started.elapsed() >= LOCK_TIMEOUT {
+                        return Err(BootstrapError::Filesystem(format!("Timed out waiting for {} to be released by another bootstrapper", path.display())));
+                    }
+                    tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+                },
+                Err(TryLockError::Error(e)) => return Err(BootstrapError::io("lock", &path, e)),
+            }
+        }
+        if waiting {
+            info("The other bootstrapper finished, continuing");
+        }
+        Ok(InstallLock::locked(file, path))
+    }
+
+    /// Takes the lock if nobody else holds it
+    pub fn try_acquire( installation_directory: &Path ) -> Result<Option<InstallLock>, BootstrapError> {
+        InstallLock::try_acquire_file(installation_directory.join(LOCK_FILENAME))
+    }
+
+    /// Takes the lock, waiting for another bootstrapper to finish if it holds it
+    pub async fn acquire( installation_directory: &Path ) -> Result<InstallLock, BootstrapError> {
+        InstallLock::acquire_file(installation_directory.join(LOCK_FILENAME), "Another SYNTAX bootstrapper is installing right now, waiting for it to finish").await
+    }
+
+    /// Takes the staging lock if nobody else holds it
+    pub fn try_acquire_staging( installation_directory: &Path ) -> Result<Option<InstallLock>, BootstrapError> {
+        InstallLock::try_acquire_file(installation_directory.join(STAGING_LOCK_FILENAME))
+    }
+
+    /// Takes the staging lock, waiting for a prefetch to finish if it holds it
+    pub async fn acquire_staging( installation_directory: &Path ) -> Result<InstallLock, BootstrapError> {
+        InstallLock::acquire_file(installation_directory.join(STAGING_LOCK_FILENAME), "SYNTAX is being downloaded in the background, waiting for it to finish").await
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop( &mut self ) {
+        debug(&format!("Released install lock {}", self.path.display().to_string().bright_blue()));
+    }
+}
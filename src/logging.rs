@@ -0,0 +1,313 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use crate::error::BootstrapError;
+use crate::events::{self, Event};
+
+// Everything printed to the console is also written, without colors, to Syntax/Logs/bootstrapper-<timestamp>.log
+// so there is something to look at after the console window closes. Prefetch runs write prefetch-<timestamp>.log
+// and are rotated on their own, a schedule running every few hours would push the launch logs out otherwise
+
+static LOG_FILE: Mutex<Option<std::fs::File>> = Mutex::new(None);
+
+// Messages above this level are dropped, both on the console and in the log file
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::DEFAULT as u8);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+    Trace = 4,
+}
+
+impl LogLevel {
+    /// Development builds show debug messages unless told otherwise
+    #[cfg(debug_assertions)]
+    pub const DEFAULT: LogLevel = LogLevel::Debug;
+    #[cfg(not(debug_assertions))]
+    pub const DEFAULT: LogLevel = LogLevel::Info;
+
+    fn from_u8( value: u8 ) -> LogLevel {
+        match value {
+            0 => LogLevel::Error,
+            1 => LogLevel::Warn,
+            2 => LogLevel::Info,
+            3 => LogLevel::Debug,
+            _ => LogLevel::Trace,
+        }
+    }
+
+    /// --verbose once shows debug messages, twice shows trace messages
+    pub fn from_verbosity( verbosity: u8 ) -> Option<LogLevel> {
+        match verbosity {
+            0 => None,
+            1 => Some(LogLevel::Debug),
+            _ => Some(LogLevel::Trace),
+        }
+    }
+}
+
+impl std::str::FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str( level: &str ) -> Result<LogLevel, String> {
+        match level.trim().to_ascii_lowercase().as_str() {
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            _ => Err(format!("Unknown log level \"{}\", expected error, warn, info, debug or trace", level)),
+        }
+    }
+}
+
+pub fn set_log_level( level: LogLevel ) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn log_level() -> LogLevel {
+    LogLevel::from_u8(LOG_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn log_enabled( level: LogLevel ) -> bool {
+    level <= log_level()
+}
+
+// Authentication tickets must never end up in a log, they are enough to take over a session
+// Values following these keys are masked, along with every secret registered at runtime
+const SENSITIVE_PREFIXES: [&str; 9] = [
+    "gameinfo:",
+    "gameinfo: ",
+    "--authenticationTicket ",
+    "t=",
+    "ticket=",
+    "authenticationTicket=",
+    "token=",
+    "password=",
+    "Authorization: ",
+];
+const REDACTED: &str = "[REDACTED]";
+
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Masks every later occurrence of the secret in log output, very short values are ignored
+pub fn register_secret( secret: &str ) {
+    if secret.len() < 4 {
+        return;
+    }
+    if let Ok(mut secrets) = SECRETS.lock() {
+        if !secrets.iter().any(|existing| existing == secret) {
+            secrets.push(secret.to_string());
+        }
+    }
+}
+
+fn is_value_delimiter( c: char ) -> bool {
+    c.is_whitespace() || matches!(c, '&' | '+' | '"' | '\'' | ')' | ']' | '\x1b')
+}
+
+/// Masks authentication tickets and other secrets in a message before it is printed or written to a file
+pub fn redact( message: &str ) -> String {
+    let mut redacted = String::with_capacity(message.len());
+    let mut rest = message;
+    'scan: while !rest.is_empty() {
+        // Keys only count at the start of a word, so "at=" or "format=" are left alone
+        let at_boundary = redacted.chars().last().map(|c| !c.is_alphanumeric() && c != '_' && c != '-').unwrap_or(true)
+            || rest.starts_with("--");
+        if at_boundary {
+            for prefix in SENSITIVE_PREFIXES.iter().rev() {
+                if let Some(after_prefix) = rest.strip_prefix(prefix) {
+                    let value_length = after_prefix.find(is_value_delimiter).unwrap_or(after_prefix.len());
+                    if value_length > 0 {
+                        redacted.push_str(prefix);
+                        redacted.push_str(REDACTED);
+                        rest = &after_prefix[value_length..];
+                        continue 'scan;
+                    }
+                }
+            }
+        }
+        let c = rest.chars().next().unwrap();
+        redacted.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if let Ok(secrets) = SECRETS.lock() {
+        for secret in secrets.iter() {
+            redacted = redacted.replace(secret.as_str(), REDACTED);
+        }
+    }
+    redacted
+}
+
+const LOG_FILE_EXTENSION: &str = "log";
+
+/// Which log files a run writes to, each kind keeps its own logging.keep_files newest files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogKind {
+    Launch,
+    Prefetch,
+}
+
+impl LogKind {
+    fn prefix( self ) -> &'static str {
+        match self {
+            LogKind::Launch => "bootstrapper-",
+            LogKind::Prefetch => "prefetch-",
+        }
+    }
+}
+
+// Messages are built with colored fragments, the escape codes have no place in a text file
+fn strip_ansi( message: &str ) -> String {
+    let mut stripped = String::with_capacity(message.len());
+    let mut chars = message.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip until the end of the escape sequence, which is always a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+fn write_to_log_file( level: &str, message: &str ) {
+    if let Ok(mut log_file) = LOG_FILE.lock() {
+        if let Some(file) = log_file.as_mut() {
+            let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+            let _ = writeln!(file, "[{}] [{}] {}", time, level, redact(&strip_ansi(message)));
+        }
+    }
+}
+
+fn log( level: LogLevel, message: &str ) {
+    if !log_enabled(level) {
+        return;
+    }
+    let (name, label) = match level {
+        LogLevel::Error => ("ERROR", "ERROR".bold().red()),
+        LogLevel::Warn => ("WARN", "WARN".bold().bright_yellow()),
+        LogLevel::Info => ("INFO", "INFO".bold().green()),
+        LogLevel::Debug => ("DEBUG", "DEBUG".bold().yellow()),
+        LogLevel::Trace => ("TRACE", "TRACE".bold().magenta()),
+    };
+    let message = redact(message);
+    if events::json_output() {
+        // stdout only carries json events in this mode
+        events::emit(Event::Log { level: name.to_ascii_lowercase(), message: strip_ansi(&message) });
+    } else {
+        let time = chrono::Local::now().format("%H:%M:%S").to_string();
+        println!("[{}] [{}] {}", time.bold().blue(), label, message);
+    }
+    write_to_log_file(name, &message);
+}
+
+pub fn error( message : &str ) {
+    log(LogLevel::Error, message);
+}
+
+pub fn warn( message : &str ) {
+    log(LogLevel::Warn, message);
+}
+
+pub fn info( message : &str ) {
+    log(LogLevel::Info, message);
+}
+
+pub fn debug( message : &str ) {
+    log(LogLevel::Debug, message);
+}
+
+pub fn trace( message : &str ) {
+    log(LogLevel::Trace, message);
+}
+
+/// Log files of the given kind in the logs directory, oldest first
+pub fn log_files( logs_directory: &Path, kind: LogKind ) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(logs_directory) else {
+        return Vec::new();
+    };
+    let mut log_files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            file_name.starts_with(kind.prefix()) && path.extension().map(|extension| extension == LOG_FILE_EXTENSION).unwrap_or(false)
+        })
+        .collect();
+    // The timestamp in the file name sorts chronologically
+    log_files.sort();
+    log_files
+}
+
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        write_to_log_file("PANIC", &panic_info.to_string());
+        default_hook(panic_info);
+    }));
+}
+
+/// Starts writing to a new log file and removes the oldest ones of the same kind so at most keep_files remain
+pub fn init_log_file( logs_directory: &Path, kind: LogKind, keep_files: usize ) -> Result<PathBuf, BootstrapError> {
+    std::fs::create_dir_all(logs_directory).map_err(|e| BootstrapError::io("create", logs_directory, e))?;
+
+    let existing_log_files = log_files(logs_directory, kind);
+    let remove_count = (existing_log_files.len() + 1).saturating_sub(keep_files.max(1));
+    for old_log_file in existing_log_files.iter().take(remove_count) {
+        if let Err(e) = std::fs::remove_file(old_log_file) {
+            debug(&format!("Failed to remove old log file {}: {}", old_log_file.display(), e));
+        }
+    }
+
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+    let log_file_path = logs_directory.join(format!("{}{}.{}", kind.prefix(), timestamp, LOG_FILE_EXTENSION));
+    let file = std::fs::File::create(&log_file_path).map_err(|e| BootstrapError::io("create", &log_file_path, e))?;
+    *LOG_FILE.lock().unwrap() = Some(file);
+    install_panic_hook();
+
+    write_to_log_file("INFO", &format!(
+        "SYNTAX Bootstrapper {} ({} {})",
+        env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH
+    ));
+    Ok(log_file_path)
+}
+
+/// Zips every log file into the logs directory so it can be attached to a support ticket
+pub fn bundle_logs( logs_directory: &Path ) -> Result<PathBuf, BootstrapError> {
+    let log_files: Vec<PathBuf> = [LogKind::Launch, LogKind::Prefetch].into_iter()
+        .flat_map(|kind| log_files(logs_directory, kind))
+        .collect();
+    if log_files.is_empty() {
+        return Err(BootstrapError::Filesystem(format!("There are no log files in {}", logs_directory.display())));
+    }
+
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let bundle_path = logs_directory.join(format!("support-logs-{}.zip", timestamp));
+    let bundle_file = std::fs::File::create(&bundle_path).map_err(|e| BootstrapError::io("create", &bundle_path, e))?;
+    let zip_error = |e: zip::result::ZipError| BootstrapError::Filesystem(format!("Failed to write {}: {}", bundle_path.display(), e));
+
+    let mut zip = zip::ZipWriter::new(bundle_file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for log_file in &log_files {
+        let file_name = log_file.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let contents = std::fs::read(log_file).map_err(|e| BootstrapError::io("read", log_file, e))?;
+        zip.start_file(file_name, options).map_err(zip_error)?;
+        zip.write_all(&contents).map_err(|e| BootstrapError::io("write", &bundle_path, e))?;
+    }
+    zip.finish().map_err(zip_error)?;
+    Ok(bundle_path)
+}
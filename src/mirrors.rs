@@ -0,0 +1,181 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::BootstrapError;
+use crate::http::{download_file, http_content_length, http_get, HttpClient};
+use crate::logging::{debug, info, warn};
+
+// Every request to the setup server goes through this list, mirrors are tried in order until one answers
+// A mirror that fails is moved to the back of the list for the rest of the run
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MirrorHealth {
+    pub host: String,
+    pub healthy: bool,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct MirrorState {
+    last_probed: i64,
+    mirrors: Vec<MirrorHealth>,
+}
+
+pub struct Mirrors {
+    hosts: Mutex<Vec<String>>,
+    state_path: PathBuf,
+}
+
+/// Mirrors are hosts served over https, unless the scheme is given explicitly ( http://localhost:8080 )
+pub fn mirror_url( host: &str, path: &str ) -> String {
+    if host.contains("://") {
+        format!("{}/{}", host.trim_end_matches('/'), path)
+    } else {
+        format!("https://{}/{}", host, path)
+    }
+}
+
+fn read_state( path: &Path ) -> Option<MirrorState> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&contents).ok()
+}
+
+impl Mirrors {
+    pub fn new( hosts: Vec<String>, installation_directory: &Path ) -> Mirrors {
+        Mirrors {
+            hosts: Mutex::new(hosts),
+            state_path: installation_directory.join("mirrors.toml"),
+        }
+    }
+
+    pub fn hosts( &self ) -> Vec<String> {
+        self.hosts.lock().unwrap().clone()
+    }
+
+    /// The mirror currently preferred, used for display purposes only
+    pub fn primary( &self ) -> String {
+        self.hosts.lock().unwrap().first().cloned().unwrap_or_default()
+    }
+
+    fn demote( &self, host: &str ) {
+        let mut hosts = self.hosts.lock().unwrap();
+        if let Some(index) = hosts.iter().position(|h| h == host) {
+            let host = hosts.remove(index);
+            hosts.push(host);
+        }
+    }
+
+    // Healthy mirrors sorted by latency come first, mirrors we know nothing about keep their configured order
+    // and unhealthy mirrors are tried last
+    fn apply_health( &self, health: &[MirrorHealth] ) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let rank = |host: &String| -> (u8, u64) {
+            match health.iter().find(|h| &h.host == host) {
+                Some(MirrorHealth { healthy: true, latency_ms: Some(latency), .. }) => (0, *latency),
+                Some(MirrorHealth { healthy: false, .. }) => (2, 0),
+                _ => (1, 0),
+            }
+        };
+        hosts.sort_by_key(rank);
+    }
+
+    /// Orders the mirrors using the last probe result, probing again if enabled and the result is older than the interval
+    pub async fn select( &self, client: &HttpClient, probe: bool, probe_interval_hours: u64 ) {
+        let state = read_state(&self.state_path);
+        let now = chrono::Utc::now().timestamp();
+        let state_is_fresh = state.as_ref()
+            .map(|state| now - state.last_probed < (probe_interval_hours * 3600) as i64)
+            .unwrap_or(false);
+
+        if probe && !state_is_fresh {
+            let health = self.probe(client).await;
+            self.apply_health(&health);
+            let state = MirrorState { last_probed: now, mirrors: health };
+            match toml::to_string(&state) {
+                Ok(contents) => {
+                    if let Err(e) = std::fs::write(&self.state_path, contents) {
+                        debug(&format!("Failed to save mirror state: {}", e));
+                    }
+                },
+                Err(e) => debug(&format!("Failed to serialize mirror state: {}", e)),
+            }
+        } else if let Some(state) = state {
+            debug("Using remembered mirror order");
+            self.apply_health(&state.mirrors);
+        }
+        debug(&format!("Mirror order: {}", self.hosts().join(", ").bright_blue()));
+    }
+
+    async fn probe( &self, client: &HttpClient ) -> Vec<MirrorHealth> {
+        info("Measuring setup mirror latency");
+        let probes = self.hosts().into_iter().map(|host| async move {
+            let started = std::time::Instant::now();
+            let request = client.get(&mirror_url(&host, "version"))
+                .timeout(std::time::Duration::from_secs(5))
+                .send()
+                .await;
+            let healthy = matches!(&request, Ok(response) if response.status().is_success());
+            let latency_ms = if healthy { Some(started.elapsed().as_millis() as u64) } else { None };
+            debug(&format!("Mirror {} healthy: {} latency: {:?}ms", host.bright_blue(), healthy, latency_ms));
+            MirrorHealth { host, healthy, latency_ms }
+        });
+        futures::future::join_all(probes).await
+    }
+
+    /// Fetches <mirror>/<path> as text from the first mirror that answers
+    pub async fn get_text( &self, client: &HttpClient, path: &str ) -> Result<String, BootstrapError> {
+        self.get_parsed(client, path, |body| Ok(body.to_string())).await
+    }
+
+    /// Like get_text, but a mirror whose answer parse rejects counts as failed and the next one is tried
+    pub async fn get_parsed<T>( &self, client: &HttpClient, path: &str, parse: impl Fn(&str) -> Result<T, String> ) -> Result<T, BootstrapError> {
+        let mut last_error = BootstrapError::Config("No setup mirrors configured".to_string());
+        for host in self.hosts() {
+            let result = match http_get(client, &mirror_url(&host, path)).await {
+                Ok(body) => parse(&body).map_err(|e| BootstrapError::Network(format!("{} returned an invalid response for {}: {}", host, path, e))),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    warn(&format!("Failed to fetch {} from {}: [{}]", path.bright_blue(), host.bright_blue(), e.to_string().bright_red()));
+                    self.demote(&host);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Size of <mirror>/<path> from the first mirror that answers a HEAD request, None when none does.
+    /// Some CDNs reject HEAD but serve the download fine, so a failure here does not demote the mirror
+    pub async fn content_length( &self, client: &HttpClient, path: &str ) -> Option<u64> {
+        for host in self.hosts() {
+            match http_content_length(client, &mirror_url(&host, path)).await {
+                Ok(content_length) => return content_length,
+                Err(e) => debug(&format!("Failed to fetch the size of {} from {}: {}", path.bright_blue(), host.bright_blue(), e)),
+            }
+        }
+        None
+    }
+
+    /// Downloads <mirror>/<path> into target, trying every mirror until one succeeds
+    pub async fn download( &self, client: &HttpClient, path: &str, target: &Path ) -> Result<(), BootstrapError> {
+        let mut last_error = BootstrapError::Config("No setup mirrors configured".to_string());
+        for host in self.hosts() {
+            match download_file(client, &mirror_url(&host, path), target).await {
+                Ok(()) => return Ok(()),
+                // Another mirror will not help if we can not write to the disk
+                Err(e @ BootstrapError::Filesystem(_)) => return Err(e),
+                Err(e) => {
+                    warn(&format!("Failed to download {} from {}: [{}]", path.bright_blue(), host.bright_blue(), e.to_string().bright_red()));
+                    self.demote(&host);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
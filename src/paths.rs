@@ -0,0 +1,141 @@
+use colored::*;
+use dirs::data_local_dir;
+use std::path::{Path, PathBuf};
+
+use crate::channel::Channel;
+use crate::error::BootstrapError;
+use crate::files::create_folder_if_not_exists;
+use crate::logging::debug;
+
+// The installation directory is picked in this order:
+//   1. --install-dir <path>
+//   2. The SYNTAX_HOME environment variable
+//   3. Portable mode, the directory of the executable when a portable.txt file is next to it
+//   4. The installation the running bootstrapper lives in ( <install>/Versions/<version>/SyntaxPlayerLauncher.exe )
+//   5. directories.install_dir from the system config file
+//   6. <local data dir>/Syntax
+
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+#[cfg(target_os = "windows")]
+pub const BOOTSTRAPPER_FILENAME: &str = "SyntaxPlayerLauncher.exe";
+#[cfg(not(target_os = "windows"))]
+pub const BOOTSTRAPPER_FILENAME: &str = "SyntaxPlayerLinuxLauncher";
+
+/// Layout of an installation directory
+///   Versions/<version>/      the bootstrapper, AppSettings.xml and one Client<year> folder per client,
+///                            prefixed with the channel for anything but stable ( Versions/beta-<version>/ )
+///   Downloads/               client zips while they are being downloaded
+///   Logs/                    one log file per run
+///   CrashReports/            zips written after the client crashed
+///   install.lock             held by the bootstrapper that is installing or updating
+///   staging.lock             held while downloading a version that is not installed yet, by prefetch or an install
+#[derive(Debug, Clone)]
+pub struct InstallationPaths {
+    pub root: PathBuf,
+    pub versions_directory: PathBuf,
+    pub downloads_directory: PathBuf,
+    pub logs_directory: PathBuf,
+    pub crash_reports_directory: PathBuf,
+    version_prefix: String,
+}
+
+impl InstallationPaths {
+    pub fn new( root: PathBuf ) -> InstallationPaths {
+        InstallationPaths {
+            versions_directory: root.join("Versions"),
+            downloads_directory: root.join("Downloads"),
+            logs_directory: root.join("Logs"),
+            crash_reports_directory: root.join("CrashReports"),
+            version_prefix: String::new(),
+            root,
+        }
+    }
+
+    /// Names version directories after the update channel
+    pub fn with_channel( mut self, channel: &Channel ) -> InstallationPaths {
+        self.version_prefix = channel.directory_prefix();
+        self
+    }
+
+    pub fn version_directory( &self, version: &str ) -> PathBuf {
+        self.versions_directory.join(format!("{}{}", self.version_prefix, version))
+    }
+
+    /// The version a directory inside Versions belongs to, None if it belongs to another channel
+    pub fn version_of_directory<'a>( &self, directory_name: &'a str ) -> Option<&'a str> {
+        directory_name.strip_prefix(self.version_prefix.as_str())
+    }
+
+    pub fn bootstrapper_path( &self, version: &str ) -> PathBuf {
+        self.version_directory(version).join(BOOTSTRAPPER_FILENAME)
+    }
+
+    pub fn app_settings_path( &self, version: &str ) -> PathBuf {
+        self.version_directory(version).join("AppSettings.xml")
+    }
+
+    /// Creates the installation, versions and downloads directories
+    pub async fn create( &self ) -> Result<(), BootstrapError> {
+        debug(&format!("Installation Directory: {}", self.root.display().to_string().bright_blue()));
+        create_folder_if_not_exists(&self.root).await?;
+        debug(&format!("Versions Directory: {}", self.versions_directory.display().to_string().bright_blue()));
+        create_folder_if_not_exists(&self.versions_directory).await?;
+        debug(&format!("Temp Downloads Directory: {}", self.downloads_directory.display().to_string().bright_blue()));
+        create_folder_if_not_exists(&self.downloads_directory).await
+    }
+}
+
+pub fn default_installation_directory() -> Result<PathBuf, String> {
+    data_local_dir()
+        .map(|dir| dir.join("Syntax"))
+        .ok_or_else(|| "Could not find the local data directory, use --install-dir or SYNTAX_HOME to choose where to install".to_string())
+}
+
+// Returns <install> when the executable is at <install>/Versions/<version>/<exe>
+fn installation_directory_of( exe_path: &Path ) -> Option<PathBuf> {
+    let version_directory = exe_path.parent()?;
+    let versions_directory = version_directory.parent()?;
+    if versions_directory.file_name()? != "Versions" {
+        return None;
+    }
+    versions_directory.parent().map(Path::to_path_buf)
+}
+
+pub fn portable_installation_directory( exe_path: &Path ) -> Option<PathBuf> {
+    let exe_directory = exe_path.parent()?;
+    if exe_directory.join(PORTABLE_MARKER).exists() {
+        return Some(exe_directory.to_path_buf());
+    }
+    // The latest bootstrapper runs from inside the portable installation
+    let installation_directory = installation_directory_of(exe_path)?;
+    if installation_directory.join(PORTABLE_MARKER).exists() {
+        return Some(installation_directory);
+    }
+    None
+}
+
+// The protocol handler is registered with a path inside the installation directory, so it must not be relative
+fn absolute( path: PathBuf ) -> PathBuf {
+    std::path::absolute(&path).unwrap_or(path)
+}
+
+/// Returns the installation directory when it is decided by the user rather than the config file
+pub fn installation_directory_override( cli_install_dir: Option<PathBuf> ) -> Option<PathBuf> {
+    if let Some(install_dir) = cli_install_dir {
+        debug(&format!("Installation directory from --install-dir: {}", install_dir.display().to_string().bright_blue()));
+        return Some(absolute(install_dir));
+    }
+    if let Some(syntax_home) = std::env::var_os("SYNTAX_HOME") {
+        if !syntax_home.is_empty() {
+            debug(&format!("Installation directory from SYNTAX_HOME: {}", syntax_home.to_string_lossy().bright_blue()));
+            return Some(absolute(PathBuf::from(syntax_home)));
+        }
+    }
+    let exe_path = std::env::current_exe().ok()?;
+    if let Some(portable_directory) = portable_installation_directory(&exe_path) {
+        debug(&format!("Running in portable mode from {}", portable_directory.display().to_string().bright_blue()));
+        return Some(portable_directory);
+    }
+    installation_directory_of(&exe_path)
+}
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::BootstrapError;
+
+#[cfg(target_os = "windows")]
+use winreg::enums::*;
+#[cfg(target_os = "windows")]
+use winreg::RegKey;
+
+// The syntax-player:// scheme always points at the bootstrapper inside the latest version directory
+
+// Exec is split on spaces, so the path is quoted the way the Desktop Entry spec asks: ", `, $ and \ are escaped
+// inside the quotes, every \ is then escaped again as part of the string value and % would start a field code
+#[cfg(not(target_os = "windows"))]
+fn desktop_exec_quote( arg: &str ) -> String {
+    let mut quoted = String::new();
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' => { quoted.push_str("\\\\"); quoted.push(c); },
+            '\\' => quoted.push_str("\\\\\\\\"),
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    format!("\"{}\"", quoted)
+}
+
+// Reverses desktop_exec_quote, also reads the unquoted Exec lines older versions wrote
+#[cfg(not(target_os = "windows"))]
+fn desktop_exec_unquote( exec: &str ) -> String {
+    let Some(quoted) = exec.strip_prefix('"').and_then(|exec| exec.rfind('"').map(|end| &exec[..end])) else {
+        return exec.trim_end_matches("%u").trim().to_string();
+    };
+    let unescaped = quoted.replace("%%", "%").replace("\\\\", "\\");
+    let mut arg = String::new();
+    let mut chars = unescaped.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => arg.extend(chars.next()),
+            _ => arg.push(c),
+        }
+    }
+    arg
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn write_desktop_file( bootstrapper_path: &Path ) -> Result<(), BootstrapError> {
+    let desktop_file_content = &format!("[Desktop Entry]
+Name=Syntax Launcher
+Exec={} %u
+Icon={}
+Type=Application
+Terminal=true
+Version={}
+MimeType=x-scheme-handler/syntax-player;", desktop_exec_quote(&bootstrapper_path.display().to_string()), bootstrapper_path.display(), env!("CARGO_PKG_VERSION"));
+
+    let desktop_file_path = desktop_file_path()?;
+    if let Some(applications_directory) = desktop_file_path.parent() {
+        std::fs::create_dir_all(applications_directory).map_err(|e| BootstrapError::io("create", applications_directory, e))?;
+    }
+    std::fs::write(&desktop_file_path, desktop_file_content).map_err(|e| BootstrapError::io("write", &desktop_file_path, e))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn desktop_file_path() -> Result<PathBuf, BootstrapError> {
+    Ok(dirs::data_local_dir()
+        .ok_or_else(|| BootstrapError::Filesystem("Could not find the local data directory".to_string()))?
+        .join("applications")
+        .join("syntax-player.desktop"))
+}
+
+/// The bootstrapper the syntax-player scheme currently opens, None when the scheme is not registered
+#[cfg(not(target_os = "windows"))]
+pub fn registered_handler() -> Option<PathBuf> {
+    let desktop_file = std::fs::read_to_string(desktop_file_path().ok()?).ok()?;
+    desktop_file.lines()
+        .find_map(|line| line.strip_prefix("Exec="))
+        .map(|exec| PathBuf::from(desktop_exec_unquote(exec)))
+}
+
+/// The bootstrapper the syntax-player scheme currently opens, None when the scheme is not registered
+#[cfg(target_os = "windows")]
+pub fn registered_handler() -> Option<PathBuf> {
+    let hkey_current_user = RegKey::predef(HKEY_CURRENT_USER);
+    let command: String = hkey_current_user.open_subkey("Software\\Classes\\syntax-player\\shell\\open\\command").ok()?.get_value("").ok()?;
+    // Written as "<bootstrapper>" "%1"
+    command.strip_prefix('"')?.split('"').next().map(PathBuf::from)
+}
+
+#[cfg(target_os = "windows")]
+pub fn register_protocol( bootstrapper_path: &Path ) -> Result<(), BootstrapError> {
+    let registry_error = |e: std::io::Error| BootstrapError::Filesystem(format!("Failed to register the syntax-player scheme: {}", e));
+    let hkey_current_user = RegKey::predef(HKEY_CURRENT_USER);
+    let hkey_classes_root : RegKey = hkey_current_user.open_subkey("Software\\Classes").map_err(registry_error)?;
+    let hkey_syntax_player = hkey_classes_root.create_subkey("syntax-player").map_err(registry_error)?.0;
+    let hkey_syntax_player_shell = hkey_syntax_player.create_subkey("shell").map_err(registry_error)?.0;
+    let hkey_syntax_player_shell_open = hkey_syntax_player_shell.create_subkey("open").map_err(registry_error)?.0;
+    let hkey_syntax_player_shell_open_command = hkey_syntax_player_shell_open.create_subkey("command").map_err(registry_error)?.0;
+    let defaulticon = hkey_syntax_player.create_subkey("DefaultIcon").map_err(registry_error)?.0;
+    hkey_syntax_player_shell_open_command.set_value("", &format!("\"{}\" \"%1\"", bootstrapper_path.display())).map_err(registry_error)?;
+    defaulticon.set_value("", &format!("\"{}\",0", bootstrapper_path.display())).map_err(registry_error)?;
+    hkey_syntax_player.set_value("", &"URL: Syntax Protocol").map_err(registry_error)?;
+    hkey_syntax_player.set_value("URL Protocol", &"").map_err(registry_error)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn register_protocol( bootstrapper_path: &Path ) -> Result<(), BootstrapError> {
+    write_desktop_file(bootstrapper_path)
+}
@@ -0,0 +1,168 @@
+use colored::*;
+use std::path::Path;
+
+use crate::error::BootstrapError;
+use crate::events;
+use crate::logging::{info, warn};
+#[cfg(not(target_os = "windows"))]
+use crate::logging::debug;
+
+// Runs "<bootstrapper> --install-dir <install> --headless prefetch" every few hours so new versions are
+// downloaded before the user clicks Play. Linux uses a systemd user timer, Windows a scheduled task.
+// The bootstrapper path changes with every version, so the schedule is written again after each update
+
+#[cfg(not(target_os = "windows"))]
+const UNIT_NAME: &str = "syntax-prefetch";
+#[cfg(target_os = "windows")]
+const TASK_NAME: &str = "SYNTAX Prefetch";
+
+fn prefetch_arguments( installation_directory: &Path ) -> Vec<String> {
+    vec![
+        "--install-dir".to_string(),
+        installation_directory.display().to_string(),
+        "--headless".to_string(),
+        "prefetch".to_string(),
+    ]
+}
+
+#[cfg(not(target_os = "windows"))]
+fn systemd_user_directory() -> Result<std::path::PathBuf, BootstrapError> {
+    dirs::config_dir()
+        .map(|config_dir| config_dir.join("systemd").join("user"))
+        .ok_or_else(|| BootstrapError::Filesystem("Could not find the config directory for the systemd timer".to_string()))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn systemctl( args: &[&str] ) {
+    debug(&format!("systemctl --user {}", args.join(" ").bright_blue()));
+    match events::run_child(std::process::Command::new("systemctl").arg("--user").args(args), "systemctl") {
+        Ok(status) if status.success() => {},
+        Ok(status) => warn(&format!("systemctl --user {} exited with {}", args.join(" "), status)),
+        Err(e) => warn(&format!("Failed to run systemctl, is systemd installed? ({})", e)),
+    }
+}
+
+// systemd splits ExecStart on whitespace, quoting keeps paths with spaces in one piece. A % starts a specifier
+// systemd would expand, %% is a literal one
+#[cfg(not(target_os = "windows"))]
+fn systemd_quote( arg: &str ) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%"))
+}
+
+/// Whether the prefetch schedule has been installed for this user
+pub fn is_installed() -> bool {
+    #[cfg(not(target_os = "windows"))]
+    {
+        systemd_user_directory().map(|directory| directory.join(format!("{}.timer", UNIT_NAME)).exists()).unwrap_or(false)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("schtasks").args(["/Query", "/TN", TASK_NAME])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+}
+
+// schtasks only takes 1 to 23 for HOURLY, longer intervals are rounded down to whole days
+#[cfg(target_os = "windows")]
+fn schtasks_schedule( interval_hours: u64 ) -> (&'static str, u64) {
+    if interval_hours < 24 {
+        ("HOURLY", interval_hours)
+    } else {
+        ("DAILY", (interval_hours / 24).min(365))
+    }
+}
+
+/// Installs ( or updates ) the schedule that runs the given bootstrapper in prefetch mode
+pub fn install( bootstrapper_path: &Path, installation_directory: &Path, interval_hours: u64 ) -> Result<(), BootstrapError> {
+    let interval_hours = interval_hours.max(1);
+    #[cfg(not(target_os = "windows"))]
+    {
+        let unit_directory = systemd_user_directory()?;
+        std::fs::create_dir_all(&unit_directory).map_err(|e| BootstrapError::io("create", &unit_directory, e))?;
+
+        let exec_start = std::iter::once(bootstrapper_path.display().to_string())
+            .chain(prefetch_arguments(installation_directory))
+            .map(|arg| systemd_quote(&arg))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let service = format!(
+            "[Unit]\nDescription=Download SYNTAX updates in the background\n\n[Service]\nType=oneshot\nExecStart={}\n",
+            exec_start
+        );
+        let timer = format!(
+            "[Unit]\nDescription=Check for SYNTAX updates every {} hours\n\n[Timer]\nOnBootSec=15min\nOnUnitActiveSec={}h\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+            interval_hours, interval_hours
+        );
+        for (extension, contents) in [("service", service), ("timer", timer)] {
+            let unit_path = unit_directory.join(format!("{}.{}", UNIT_NAME, extension));
+            std::fs::write(&unit_path, contents).map_err(|e| BootstrapError::io("write", &unit_path, e))?;
+        }
+        systemctl(&["daemon-reload"]);
+        systemctl(&["enable", "--now", &format!("{}.timer", UNIT_NAME)]);
+        info(&format!("Installed the {} systemd user timer, updates are checked every {} hours", UNIT_NAME.bright_blue(), interval_hours));
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let task_command = std::iter::once(bootstrapper_path.display().to_string())
+            .chain(prefetch_arguments(installation_directory))
+            .map(|arg| if arg.starts_with("--") || arg == "prefetch" { arg } else { format!("\"{}\"", arg) })
+            .collect::<Vec<String>>()
+            .join(" ");
+        let (schedule_type, modifier) = schtasks_schedule(interval_hours);
+        let status = events::run_child(
+            std::process::Command::new("schtasks").args(["/Create", "/F", "/SC", schedule_type, "/MO", &modifier.to_string(), "/TN", TASK_NAME, "/TR", &task_command]),
+            "schtasks"
+        )
+            .map_err(|e| BootstrapError::Launch(format!("Failed to run schtasks: {}", e)))?;
+        if !status.success() {
+            return Err(BootstrapError::Launch(format!("schtasks exited with {} while creating the {} task", status, TASK_NAME)));
+        }
+        let interval = if schedule_type == "DAILY" { format!("{} days", modifier) } else { format!("{} hours", modifier) };
+        info(&format!("Installed the {} scheduled task, updates are checked every {}", TASK_NAME.bright_blue(), interval));
+    }
+    Ok(())
+}
+
+/// Points an installed schedule at the bootstrapper of a freshly installed version, failing to is only worth a warning
+pub fn refresh( bootstrapper_path: &Path, installation_directory: &Path, interval_hours: u64 ) {
+    if !is_installed() {
+        return;
+    }
+    if let Err(e) = install(bootstrapper_path, installation_directory, interval_hours) {
+        warn(&format!("Failed to update the prefetch schedule: {}", e));
+    }
+}
+
+/// Removes the prefetch schedule, does nothing if it was never installed
+pub fn remove() -> Result<(), BootstrapError> {
+    if !is_installed() {
+        info("The prefetch schedule is not installed");
+        return Ok(());
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        systemctl(&["disable", "--now", &format!("{}.timer", UNIT_NAME)]);
+        let unit_directory = systemd_user_directory()?;
+        for extension in ["service", "timer"] {
+            let unit_path = unit_directory.join(format!("{}.{}", UNIT_NAME, extension));
+            if unit_path.exists() {
+                std::fs::remove_file(&unit_path).map_err(|e| BootstrapError::io("remove", &unit_path, e))?;
+            }
+        }
+        systemctl(&["daemon-reload"]);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let status = events::run_child(std::process::Command::new("schtasks").args(["/Delete", "/F", "/TN", TASK_NAME]), "schtasks")
+            .map_err(|e| BootstrapError::Launch(format!("Failed to run schtasks: {}", e)))?;
+        if !status.success() {
+            return Err(BootstrapError::Launch(format!("schtasks exited with {} while removing the {} task", status, TASK_NAME)));
+        }
+    }
+    info("Removed the prefetch schedule");
+    Ok(())
+}
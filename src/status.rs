@@ -0,0 +1,123 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::error::BootstrapError;
+use crate::files::directory_size;
+use crate::http::redact_proxy;
+use crate::installer::{Installer, CLIENT_YEARS};
+use crate::paths::InstallationPaths;
+use crate::protocol::registered_handler;
+use crate::updater::Updater;
+
+// Everything `syntax_bootstrapper status` reports, gathered without changing anything on disk
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ClientStatus {
+    pub year: String,
+    pub installed: bool,
+    pub size_bytes: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RunnerStatus {
+    pub command: String,
+    /// Set in winepath.txt instead of wine from the PATH
+    pub custom: bool,
+    /// None when the runner could not be started
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Status {
+    pub installation_directory: PathBuf,
+    pub channel: String,
+    pub installed_version: Option<String>,
+    /// Downloaded by prefetch, used on the next launch
+    pub prefetched_version: Option<String>,
+    pub latest_version: Option<String>,
+    /// Why the latest version is unknown
+    pub latest_version_error: Option<String>,
+    /// None when either version is unknown
+    pub up_to_date: Option<bool>,
+    pub clients: Vec<ClientStatus>,
+    pub protocol_handler: Option<PathBuf>,
+    /// Whether the syntax-player scheme opens the bootstrapper of the installed version
+    pub protocol_handler_current: bool,
+    /// Only on Linux, Windows runs the client directly
+    pub runner: Option<RunnerStatus>,
+    /// The config in effect, after the config files and environment variables were applied
+    pub config: Config,
+}
+
+// Proxy urls may carry a password, which has no business in a support ticket
+fn redacted_config( config: &Config ) -> Config {
+    let mut config = config.clone();
+    config.network.proxy = config.network.proxy.as_deref().map(redact_proxy);
+    config
+}
+
+#[cfg(not(target_os = "windows"))]
+fn runner_status( paths: &InstallationPaths ) -> Result<Option<RunnerStatus>, BootstrapError> {
+    let custom_wine_binary = crate::launcher::custom_wine_binary(&paths.root)?;
+    let custom = custom_wine_binary.is_some();
+    let command = custom_wine_binary.unwrap_or_else(|| "wine".to_string());
+    let version = crate::launcher::wine_version(&command);
+    Ok(Some(RunnerStatus { command, custom, version }))
+}
+
+#[cfg(target_os = "windows")]
+fn runner_status( _paths: &InstallationPaths ) -> Result<Option<RunnerStatus>, BootstrapError> {
+    Ok(None)
+}
+
+impl Status {
+    /// Asks the setup servers for the latest version, a failure is reported instead of returned
+    pub async fn collect( config: &Config, paths: &InstallationPaths, installer: &Installer, updater: &Updater ) -> Result<Status, BootstrapError> {
+        let installed_versions = installer.installed_versions();
+        let installed_version = installed_versions.iter().find(|version| !installer.is_staged(version)).cloned();
+        let prefetched_version = installed_versions.iter().find(|version| installer.is_staged(version)).cloned();
+
+        let (latest_version, latest_version_error) = match updater.latest_version().await {
+            Ok(latest_version) => (Some(latest_version), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let up_to_date = match (&installed_version, &latest_version) {
+            (Some(installed_version), Some(latest_version)) => Some(installed_version == latest_version),
+            _ => None,
+        };
+
+        let clients = match &installed_version {
+            Some(version) => CLIENT_YEARS.iter().map(|client_year| {
+                let client_directory = paths.version_directory(version).join(format!("Client{}", client_year));
+                ClientStatus {
+                    year: client_year.to_string(),
+                    installed: client_directory.join("SyntaxPlayerBeta.exe").is_file(),
+                    size_bytes: directory_size(&client_directory),
+                }
+            }).collect(),
+            None => Vec::new(),
+        };
+
+        let protocol_handler = registered_handler();
+        let protocol_handler_current = match (&protocol_handler, &installed_version) {
+            (Some(protocol_handler), Some(version)) => protocol_handler == &paths.bootstrapper_path(version),
+            _ => false,
+        };
+
+        Ok(Status {
+            installation_directory: paths.root.clone(),
+            channel: config.updates.channel().map_err(BootstrapError::Config)?.to_string(),
+            installed_version,
+            prefetched_version,
+            latest_version,
+            latest_version_error,
+            up_to_date,
+            clients,
+            protocol_handler,
+            protocol_handler_current,
+            runner: runner_status(paths)?,
+            config: redacted_config(config),
+        })
+    }
+}
@@ -0,0 +1,141 @@
+use colored::*;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::channel::Channel;
+use crate::error::BootstrapError;
+use crate::files::{create_folder_if_not_exists, get_sha1_hash_of_file};
+use crate::http::HttpClient;
+use crate::logging::{debug, info};
+use crate::mirrors::Mirrors;
+use crate::paths::{InstallationPaths, BOOTSTRAPPER_FILENAME};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateAction {
+    /// The running bootstrapper is the latest one, continue with the install
+    UpToDate,
+    /// The latest bootstrapper was started ( or has to be started from the website ), this one should exit.
+    /// pause is set when a message was printed that should stay readable for a while, which the caller does
+    /// once it let go of the install lock
+    Relaunched { pause: bool },
+}
+
+/// Versions are used as directory names and in download urls, so anything but "version-<hex>" is rejected.
+/// Surrounding whitespace is trimmed, the case is kept since the version ends up in case sensitive download urls
+pub fn normalise_version( raw: &str ) -> Result<String, String> {
+    let version = raw.trim().to_string();
+    match version.strip_prefix("version-") {
+        Some(hash) if !hash.is_empty() && hash.len() <= 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) => Ok(version),
+        _ => {
+            // Error pages can be long, a little of it is enough to tell what the server sent
+            let preview: String = raw.trim().chars().take(40).filter(|c| !c.is_control()).collect();
+            Err(format!("expected a version like version-0123456789abcdef, got \"{}\"", preview))
+        },
+    }
+}
+
+pub struct Updater {
+    client: HttpClient,
+    mirrors: Arc<Mirrors>,
+    paths: InstallationPaths,
+    channel: Channel,
+}
+
+impl Updater {
+    pub fn new( client: HttpClient, mirrors: Arc<Mirrors>, paths: InstallationPaths, channel: Channel ) -> Updater {
+        Updater { client, mirrors, paths, channel }
+    }
+
+    /// The version to install, the latest one of the channel or the pinned one
+    pub async fn latest_version( &self ) -> Result<String, BootstrapError> {
+        let version_path = match &self.channel {
+            Channel::Stable => "version".to_string(),
+            Channel::Named(name) => format!("version?channel={}", name),
+            Channel::Pinned(pinned_version) => {
+                info(&format!("Pinned Client Version: {}", pinned_version.cyan().underline()));
+                return Ok(pinned_version.clone());
+            },
+        };
+        debug(&format!("Fetching latest client version of the {} channel from setup server", self.channel));
+        let latest_client_version = self.mirrors.get_parsed(&self.client, &version_path, normalise_version).await?;
+        info(&format!("Latest Client Version: {}", latest_client_version.cyan().underline()));
+        debug(&format!("Setup Server: {}", self.mirrors.primary().cyan().underline()));
+        Ok(latest_client_version)
+    }
+
+    async fn download_bootstrapper( &self, version: &str, target: &Path ) -> Result<(), BootstrapError> {
+        self.mirrors.download(&self.client, &format!("{}-{}", version, BOOTSTRAPPER_FILENAME), target).await
+    }
+
+    /// Downloads the bootstrapper of the given version into its version directory unless it is already there
+    pub async fn fetch_bootstrapper( &self, version: &str ) -> Result<(), BootstrapError> {
+        let latest_bootstrapper_path = self.paths.bootstrapper_path(version);
+        if latest_bootstrapper_path.exists() {
+            return Ok(());
+        }
+        create_folder_if_not_exists(&self.paths.version_directory(version)).await?;
+        info("Downloading the latest bootstrapper");
+        self.download_bootstrapper(version, &latest_bootstrapper_path).await
+    }
+
+    /// Makes sure the bootstrapper of the given version is the one running, starting it with the same arguments if not
+    pub async fn ensure_latest_bootstrapper( &self, version: &str, args: &[String] ) -> Result<UpdateAction, BootstrapError> {
+        let current_version_directory = self.paths.version_directory(version);
+        debug(&format!("Current Version Directory: {}", current_version_directory.display().to_string().bright_blue()));
+        create_folder_if_not_exists(&current_version_directory).await?;
+
+        let latest_bootstrapper_path = self.paths.bootstrapper_path(version);
+        // Is the program currently running from the latest version directory?
+        let current_exe_path = std::env::current_exe()
+            .map_err(|e| BootstrapError::Filesystem(format!("Failed to find the running executable: {}", e)))?;
+        if current_exe_path.starts_with(&current_version_directory) {
+            return Ok(UpdateAction::UpToDate);
+        }
+
+        // Check if the latest bootstrapper is downloaded
+        self.fetch_bootstrapper(version).await?;
+
+        // Lets compare the SHA1 hash of the latest bootstrapper to the one we are currently running
+        // If they are the same, then we can continue with the update process
+        // We do this because antivirus software does not like this type of behavior
+        let latest_bootstrapper_hash = get_sha1_hash_of_file(&latest_bootstrapper_path).await?;
+        let current_exe_hash = get_sha1_hash_of_file(&current_exe_path).await?;
+
+        debug(&format!("Latest Bootstrapper Hash: {}", latest_bootstrapper_hash.bright_blue()));
+        debug(&format!("Current Bootstrapper Hash: {}", current_exe_hash.bright_blue()));
+
+        if latest_bootstrapper_hash == current_exe_hash {
+            return Ok(UpdateAction::UpToDate);
+        }
+
+        info("Starting latest bootstrapper");
+        // Run the latest bootstrapper ( with the same arguments passed to us ) and exit
+        #[cfg(target_os = "windows")]
+        {
+            let mut command = std::process::Command::new(&latest_bootstrapper_path);
+            command.args(args);
+            if let Err(e) = command.spawn() {
+                debug(&format!("Bootstrapper errored with error {}", e));
+                info("Found bootstrapper was corrupted! Downloading...");
+                std::fs::remove_file(&latest_bootstrapper_path).map_err(|e| BootstrapError::io("remove", &latest_bootstrapper_path, e))?;
+                self.download_bootstrapper(version, &latest_bootstrapper_path).await?;
+                command.spawn().map_err(|e| BootstrapError::Integrity(format!("The latest bootstrapper is still corrupted: {}", e)))?;
+                return Ok(UpdateAction::Relaunched { pause: true });
+            }
+            Ok(UpdateAction::Relaunched { pause: false })
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            let _ = args;
+            // Make sure the latest bootstrapper is executable
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&latest_bootstrapper_path, std::fs::Permissions::from_mode(0o755))
+                .map_err(|e| BootstrapError::io("make executable", &latest_bootstrapper_path, e))?;
+
+            crate::protocol::write_desktop_file(&latest_bootstrapper_path)?;
+
+            info("Please launch SYNTAX from the website, to continue with the update process.");
+            Ok(UpdateAction::Relaunched { pause: true })
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use colored::*;
+
+use crate::logging::{debug, register_secret};
+
+// The website launches the bootstrapper with a uri that looks something like
+// "syntax-player://1+launchmode:play+gameinfo:TICKET+placelauncherurl:https://www.syntax.eco/Game/placelauncher.ashx?placeId=660&t=TICKET+k:l"
+// Every "+" separated part is a key:value pair, values may contain ":" themselves
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchRequest {
+    pub launch_mode: String,
+    pub authentication_ticket: String,
+    pub join_script: String,
+    pub client_year: String,
+}
+
+// The join script url carries the ticket again as ?t=TICKET
+fn register_query_secrets( url: &str ) {
+    let Some((_, query)) = url.split_once('?') else {
+        return;
+    };
+    for parameter in query.split('&') {
+        if let Some(("t", value)) = parameter.split_once('=') {
+            register_secret(value);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UriParser {
+    scheme: String,
+}
+
+impl Default for UriParser {
+    fn default() -> Self {
+        UriParser::new("syntax-player")
+    }
+}
+
+impl UriParser {
+    pub fn new( scheme: &str ) -> UriParser {
+        UriParser { scheme: format!("{}://", scheme) }
+    }
+
+    pub fn parse( &self, uri: &str ) -> LaunchRequest {
+        let main_args = uri.replace(&self.scheme, "");
+        let mut request = LaunchRequest::default();
+
+        for arg in main_args.split('+') {
+            let (key, value) = arg.split_once(':').unwrap_or((arg, ""));
+            // Secrets are registered before anything is logged so the debug line below is already masked
+            match key {
+                "launchmode" => {
+                    request.launch_mode = value.to_string();
+                },
+                "gameinfo" => {
+                    register_secret(value);
+                    request.authentication_ticket = value.to_string();
+                },
+                "placelauncherurl" => {
+                    register_query_secrets(value);
+                    request.join_script = value.to_string();
+                },
+                "clientyear" => {
+                    request.client_year = value.to_string();
+                },
+                _ => {}
+            }
+            debug(&format!("{}: {}", key.bright_blue(), value.bright_blue()));
+        }
+        request
+    }
+}
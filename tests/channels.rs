@@ -0,0 +1,55 @@
+// Update channels and pinned versions
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+
+const BETA_VERSION: &str = "version-bbbb0123456789ab";
+
+#[test]
+fn beta_channel_installs_into_channel_directory() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    server.serve_release(BETA_VERSION);
+    server.serve("/version", VERSION.as_bytes().to_vec());
+    server.serve("/version?channel=beta", BETA_VERSION.as_bytes().to_vec());
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    std::fs::write(environment.install_dir.join("config.toml"), "[behaviour]\nopen_landing_page = false\n\n[updates]\nchannel = \"beta\"\n").unwrap();
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    assert!(server.requests().contains(&"/version?channel=beta".to_string()));
+    let beta_directory = environment.install_dir.join("Versions").join(format!("beta-{}", BETA_VERSION));
+    assert!(beta_directory.join("AppSettings.xml").exists());
+    assert!(!environment.version_directory(VERSION).exists());
+    assert_eq!(
+        environment.launched_command_line()[0],
+        beta_directory.join("Client2018").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+}
+
+#[test]
+fn pinned_version_skips_version_lookup() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    server.serve("/version", b"version-ffffffffffffffff".to_vec());
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.command(&[&play_uri("2018")]).env("SYNTAX_PINNED_VERSION", VERSION).output().unwrap();
+    assert_success(&output);
+
+    assert!(!server.requests().contains(&"/version".to_string()));
+    let pinned_directory = environment.install_dir.join("Versions").join(format!("pinned-{}", VERSION));
+    assert!(pinned_directory.join("Client2018").join("SyntaxPlayerBeta.exe").exists());
+}
+
+#[test]
+fn invalid_channel_is_a_config_error() {
+    let environment = TestEnvironment::new(Vec::new());
+
+    let output = environment.command(&[&play_uri("2018")]).env("SYNTAX_CHANNEL", "../beta").output().unwrap();
+    assert_eq!(output.status.code(), Some(6));
+
+    // Would share Versions/pinned-<version> with pinned versions
+    let output = environment.command(&[&play_uri("2018")]).env("SYNTAX_CHANNEL", "Pinned").output().unwrap();
+    assert_eq!(output.status.code(), Some(6));
+}
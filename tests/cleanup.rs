@@ -0,0 +1,143 @@
+// Garbage collection of interrupted downloads and stale versions, on startup and with the clean command
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+const OLD_VERSION: &str = "version-00000000000000aa";
+const HALF_INSTALLED_VERSION: &str = "version-00000000000000bb";
+const STAGED_VERSION: &str = "version-00000000000000cc";
+const PARTIAL_DOWNLOAD: &str = "0123456789abcdef0123456789abcdef";
+
+// A complete version installed an hour before the current one
+fn fake_old_version( environment: &TestEnvironment, version: &str ) {
+    let version_directory = environment.version_directory(version);
+    std::fs::create_dir_all(version_directory.join("Client2018")).unwrap();
+    std::fs::write(version_directory.join("Client2018").join("SyntaxPlayerBeta.exe"), vec![0; 4096]).unwrap();
+    let app_settings_path = version_directory.join("AppSettings.xml");
+    std::fs::write(&app_settings_path, "<Settings></Settings>").unwrap();
+    let file = std::fs::File::options().write(true).open(&app_settings_path).unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(3600)).unwrap();
+}
+
+// What an interrupted run leaves behind
+fn fake_leftovers( environment: &TestEnvironment ) {
+    let downloads_directory = environment.install_dir.join("Downloads");
+    std::fs::create_dir_all(&downloads_directory).unwrap();
+    std::fs::write(downloads_directory.join(PARTIAL_DOWNLOAD), vec![0; 8192]).unwrap();
+    let half_installed = environment.version_directory(HALF_INSTALLED_VERSION).join("Client2018");
+    std::fs::create_dir_all(&half_installed).unwrap();
+    std::fs::write(half_installed.join("SyntaxPlayerBeta.exe"), vec![0; 1024]).unwrap();
+}
+
+fn assert_removed( path: &Path ) {
+    assert!(!path.exists(), "{} should have been removed", path.display());
+}
+
+#[test]
+fn clean_removes_leftovers_and_stale_versions() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[]));
+
+    fake_leftovers(&environment);
+    fake_old_version(&environment, OLD_VERSION);
+    fake_old_version(&environment, STAGED_VERSION);
+    std::fs::write(environment.version_directory(STAGED_VERSION).join("staged"), "").unwrap();
+    let other_channel = environment.install_dir.join("Versions").join(format!("beta-{}", OLD_VERSION));
+    std::fs::create_dir_all(&other_channel).unwrap();
+
+    let output = environment.run(&["clean"]);
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Freed"), "stdout:\n{}", stdout);
+
+    assert_removed(&environment.install_dir.join("Downloads").join(PARTIAL_DOWNLOAD));
+    assert_removed(&environment.version_directory(HALF_INSTALLED_VERSION));
+    assert_removed(&environment.version_directory(OLD_VERSION));
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").is_file());
+    assert!(environment.version_directory(STAGED_VERSION).join("AppSettings.xml").is_file(), "prefetched versions are kept");
+    assert!(other_channel.is_dir(), "versions of other channels are left alone");
+    // clean never talks to the setup servers
+    assert_eq!(server.requests().iter().filter(|path| path.as_str() == "/version").count(), 1);
+
+    let output = environment.run(&["clean"]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Nothing to clean up"));
+}
+
+#[test]
+fn startup_removes_leftovers_before_launching() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[]));
+
+    fake_leftovers(&environment);
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Cleaned up 2 stale files and folders"));
+    assert_removed(&environment.install_dir.join("Downloads").join(PARTIAL_DOWNLOAD));
+    assert_removed(&environment.version_directory(HALF_INSTALLED_VERSION));
+    assert!(!environment.launched_command_line().is_empty());
+}
+
+#[test]
+fn cleanup_config_is_respected() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    std::fs::write(
+        environment.install_dir.join("config.toml"),
+        "[behaviour]\nclear_screen = false\nopen_landing_page = false\n\n[cleanup]\non_startup = false\nkeep_versions = 2\n"
+    ).unwrap();
+    assert_success(&environment.run(&[]));
+
+    fake_leftovers(&environment);
+    fake_old_version(&environment, OLD_VERSION);
+    assert_success(&environment.run(&[]));
+    assert!(environment.install_dir.join("Downloads").join(PARTIAL_DOWNLOAD).is_file(), "on_startup = false skips the cleanup");
+
+    assert_success(&environment.run(&["clean"]));
+    assert_removed(&environment.install_dir.join("Downloads").join(PARTIAL_DOWNLOAD));
+    assert_removed(&environment.version_directory(HALF_INSTALLED_VERSION));
+    assert!(environment.version_directory(OLD_VERSION).join("AppSettings.xml").is_file(), "keep_versions = 2 keeps the previous version");
+}
+
+#[test]
+fn version_bump_keeps_versions_and_other_channels() {
+    const SECOND_VERSION: &str = "version-00000000000000dd";
+    const THIRD_VERSION: &str = "version-00000000000000ee";
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    std::fs::write(
+        environment.install_dir.join("config.toml"),
+        "[behaviour]\nclear_screen = false\nopen_landing_page = false\n\n[cleanup]\nkeep_versions = 2\n"
+    ).unwrap();
+    assert_success(&environment.run(&[]));
+    let other_channels: Vec<_> = ["beta", "pinned"].iter()
+        .map(|prefix| environment.install_dir.join("Versions").join(format!("{}-{}", prefix, OLD_VERSION)))
+        .collect();
+    for other_channel in &other_channels {
+        std::fs::create_dir_all(other_channel).unwrap();
+        std::fs::write(other_channel.join("AppSettings.xml"), "<Settings></Settings>").unwrap();
+    }
+
+    server.serve_release(SECOND_VERSION);
+    assert_success(&environment.run(&[]));
+    assert!(environment.version_directory(SECOND_VERSION).join("AppSettings.xml").is_file());
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").is_file(), "keep_versions = 2 keeps the previous version");
+
+    server.serve_release(THIRD_VERSION);
+    let output = environment.run(&[]);
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Removed 1 old versions, freeing"), "the freed space is reported:\n{}", stdout);
+    assert!(environment.version_directory(THIRD_VERSION).join("AppSettings.xml").is_file());
+    assert!(environment.version_directory(SECOND_VERSION).join("AppSettings.xml").is_file());
+    assert_removed(&environment.version_directory(VERSION));
+    for other_channel in &other_channels {
+        assert!(other_channel.join("AppSettings.xml").is_file(), "{} belongs to another channel", other_channel.display());
+    }
+}
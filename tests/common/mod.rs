@@ -0,0 +1,351 @@
+// Shared harness for the end to end tests
+// A tiny http(s) server stands in for setup.syntax.eco and the bootstrapper binary is run against it with
+// every directory it touches redirected into a temporary directory
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const VERSION: &str = "version-0123456789abcdef";
+pub const BOOTSTRAPPER_FILENAME: &str = "SyntaxPlayerLinuxLauncher";
+pub const CLIENT_YEARS: [&str; 5] = ["2018", "2020", "2014", "2016", "2021"];
+pub const TICKET: &str = "SECRETTICKET";
+
+pub struct MockSetupServer {
+    pub address: String,
+    /// PEM of the self-signed certificate a server started with start_https() presents
+    pub certificate: Option<String>,
+    state: Arc<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+    files: Mutex<HashMap<String, Vec<u8>>>,
+    requests: Mutex<Vec<String>>,
+    user_agents: Mutex<Vec<String>>,
+    delay: Mutex<Duration>,
+    chunked: Mutex<bool>,
+    // Sizes claimed by HEAD responses instead of the real ones
+    head_lengths: Mutex<HashMap<String, u64>>,
+    // Answer HEAD requests with 405 like some CDNs do
+    reject_head: Mutex<bool>,
+}
+
+fn handle_connection<S: Read + Write>( stream: &mut S, state: &MockState ) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Only the user agent is kept from the headers, requests never have a body
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).is_err() || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("user-agent") {
+                state.user_agents.lock().unwrap().push(value.trim().to_string());
+            }
+        }
+    }
+    // Requests never have a body, nothing is left in the buffer
+    let stream = reader.get_mut();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let mut path = parts.next().unwrap_or_default().to_string();
+    // Requests sent through a proxy carry the whole url, the server doubles as that proxy
+    if let Some((_, url)) = path.split_once("://") {
+        path = url.find('/').map(|index| url[index..].to_string()).unwrap_or_else(|| "/".to_string());
+    }
+    // Only downloads are logged, the size checks would throw off tests counting them
+    if method != "HEAD" {
+        state.requests.lock().unwrap().push(path.clone());
+    }
+
+    if method == "HEAD" && *state.reject_head.lock().unwrap() {
+        let _ = stream.write_all(b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        return;
+    }
+    let body = state.files.lock().unwrap().get(&path).cloned();
+    let (status, body) = match body {
+        Some(body) => ("200 OK", body),
+        None => ("404 Not Found", b"<html>Not Found</html>".to_vec()),
+    };
+    if *state.chunked.lock().unwrap() {
+        let header = format!("HTTP/1.1 {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n", status);
+        let _ = stream.write_all(header.as_bytes());
+        if method != "HEAD" {
+            for chunk in body.chunks(1024) {
+                let _ = stream.write_all(format!("{:x}\r\n", chunk.len()).as_bytes());
+                let _ = stream.write_all(chunk);
+                let _ = stream.write_all(b"\r\n");
+            }
+            let _ = stream.write_all(b"0\r\n\r\n");
+        }
+    } else if method == "HEAD" {
+        let content_length = state.head_lengths.lock().unwrap().get(&path).copied().unwrap_or(body.len() as u64);
+        let header = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, content_length);
+        let _ = stream.write_all(header.as_bytes());
+    } else {
+        let header = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+        let _ = stream.write_all(header.as_bytes());
+        let _ = stream.write_all(&body);
+    }
+    let _ = stream.flush();
+}
+
+// A certificate for 127.0.0.1 that signs itself, so it doubles as the certificate authority to trust
+fn self_signed_certificate() -> (openssl::x509::X509, openssl::pkey::PKey<openssl::pkey::Private>) {
+    use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+    let group = openssl::ec::EcGroup::from_curve_name(openssl::nid::Nid::X9_62_PRIME256V1).unwrap();
+    let key = openssl::pkey::PKey::from_ec_key(openssl::ec::EcKey::generate(&group).unwrap()).unwrap();
+    let mut name = openssl::x509::X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", "SYNTAX Mock Setup Server").unwrap();
+    let name = name.build();
+
+    let mut builder = openssl::x509::X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_serial_number(&openssl::bn::BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder.set_not_before(&openssl::asn1::Asn1Time::days_from_now(0).unwrap()).unwrap();
+    builder.set_not_after(&openssl::asn1::Asn1Time::days_from_now(1).unwrap()).unwrap();
+    builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+    let subject_alternative_name = SubjectAlternativeName::new().ip("127.0.0.1").dns("localhost").build(&builder.x509v3_context(None, None)).unwrap();
+    builder.append_extension(subject_alternative_name).unwrap();
+    builder.sign(&key, openssl::hash::MessageDigest::sha256()).unwrap();
+    (builder.build(), key)
+}
+
+impl MockSetupServer {
+    pub fn start() -> MockSetupServer {
+        MockSetupServer::listen(None)
+    }
+
+    /// Serves https:// with a freshly generated self-signed certificate, see certificate
+    pub fn start_https() -> MockSetupServer {
+        let (certificate, key) = self_signed_certificate();
+        let mut acceptor = openssl::ssl::SslAcceptor::mozilla_intermediate_v5(openssl::ssl::SslMethod::tls_server()).unwrap();
+        acceptor.set_private_key(&key).unwrap();
+        acceptor.set_certificate(&certificate).unwrap();
+        let mut server = MockSetupServer::listen(Some(Arc::new(acceptor.build())));
+        server.certificate = Some(String::from_utf8(certificate.to_pem().unwrap()).unwrap());
+        server
+    }
+
+    fn listen( acceptor: Option<Arc<openssl::ssl::SslAcceptor>> ) -> MockSetupServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let scheme = if acceptor.is_some() { "https" } else { "http" };
+        let address = format!("{}://{}", scheme, listener.local_addr().unwrap());
+        let state = Arc::new(MockState::default());
+        let thread_state = state.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let state = thread_state.clone();
+                let acceptor = acceptor.clone();
+                std::thread::spawn(move || {
+                    let delay = *state.delay.lock().unwrap();
+                    std::thread::sleep(delay);
+                    match acceptor {
+                        // A client that does not trust the certificate gives up during the handshake
+                        Some(acceptor) => if let Ok(mut stream) = acceptor.accept(stream) {
+                            handle_connection(&mut stream, &state);
+                            let _ = stream.shutdown();
+                        },
+                        None => handle_connection(&mut stream, &state),
+                    }
+                });
+            }
+        });
+        MockSetupServer { address, certificate: None, state }
+    }
+
+    /// Serves /version, the running bootstrapper binary and a small zip for every client year
+    pub fn start_with_release( version: &str ) -> MockSetupServer {
+        let server = MockSetupServer::start();
+        server.serve_release(version);
+        server
+    }
+
+    pub fn serve( &self, path: &str, body: Vec<u8> ) {
+        self.state.files.lock().unwrap().insert(path.to_string(), body);
+    }
+
+    pub fn remove( &self, path: &str ) {
+        self.state.files.lock().unwrap().remove(path);
+    }
+
+    pub fn serve_release( &self, version: &str ) {
+        self.serve("/version", version.as_bytes().to_vec());
+        self.serve(&format!("/{}-{}", version, BOOTSTRAPPER_FILENAME), std::fs::read(bootstrapper_binary()).unwrap());
+        for client_year in CLIENT_YEARS {
+            self.serve(&format!("/{}-{}client.zip", version, client_year), client_zip(client_year));
+        }
+    }
+
+    /// Waits this long before answering every request, to keep an install running for a while
+    pub fn set_delay( &self, delay: Duration ) {
+        *self.state.delay.lock().unwrap() = delay;
+    }
+
+    /// Answers with Transfer-Encoding: chunked instead of a Content-Length
+    pub fn set_chunked( &self, chunked: bool ) {
+        *self.state.chunked.lock().unwrap() = chunked;
+    }
+
+    /// Makes HEAD requests for path claim this size, downloads still get the real file
+    pub fn set_head_length( &self, path: &str, content_length: u64 ) {
+        self.state.head_lengths.lock().unwrap().insert(path.to_string(), content_length);
+    }
+
+    pub fn set_reject_head( &self, reject_head: bool ) {
+        *self.state.reject_head.lock().unwrap() = reject_head;
+    }
+
+    pub fn requests( &self ) -> Vec<String> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    pub fn user_agents( &self ) -> Vec<String> {
+        self.state.user_agents.lock().unwrap().clone()
+    }
+}
+
+pub fn bootstrapper_binary() -> PathBuf {
+    PathBuf::from(env!("CARGO_BIN_EXE_syntax_bootstrapper"))
+}
+
+pub fn client_zip( client_year: &str ) -> Vec<u8> {
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    {
+        let mut zip = zip::ZipWriter::new(&mut buffer);
+        let options = zip::write::FileOptions::default();
+        zip.start_file("SyntaxPlayerBeta.exe", options).unwrap();
+        zip.write_all(format!("client {}", client_year).as_bytes()).unwrap();
+        zip.add_directory("content/", options).unwrap();
+        zip.start_file("content/fonts.txt", options).unwrap();
+        zip.write_all(b"fonts").unwrap();
+        zip.finish().unwrap();
+    }
+    buffer.into_inner()
+}
+
+/// A temporary home for one bootstrapper run
+pub struct TestEnvironment {
+    pub root: tempfile::TempDir,
+    pub install_dir: PathBuf,
+    pub data_dir: PathBuf,
+    pub config_dir: PathBuf,
+    pub launch_log: PathBuf,
+    pub mirrors: Vec<String>,
+}
+
+impl TestEnvironment {
+    pub fn new( mirrors: Vec<String> ) -> TestEnvironment {
+        let root = tempfile::tempdir().unwrap();
+        let install_dir = root.path().join("Syntax");
+        let data_dir = root.path().join("data");
+        let config_dir = root.path().join("config");
+        let launch_log = root.path().join("launch.log");
+        std::fs::create_dir_all(&install_dir).unwrap();
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        // The user config keeps the terminal alone while the tests run
+        std::fs::write(install_dir.join("config.toml"), "[behaviour]\nclear_screen = false\nopen_landing_page = false\n").unwrap();
+
+        // A fake wine that writes the command line it was started with and appends the client to launches.log,
+        // FAKE_WINE_EXIT sets its exit code and FAKE_WINE_OUTPUT is printed to stdout like the noise wine and the client write
+        let fake_wine = root.path().join("fake-wine");
+        std::fs::write(&fake_wine, format!(
+            "#!/bin/sh\nif [ \"$1\" = \"--version\" ]; then echo wine-9.0-fake; exit 0; fi\nprintf '%s\\n' \"$@\" > \"{}\"\necho \"$1\" >> \"{}\"\n[ -n \"$FAKE_WINE_OUTPUT\" ] && echo \"$FAKE_WINE_OUTPUT\"\nexit ${{FAKE_WINE_EXIT:-0}}\n",
+            launch_log.display(),
+            launch_log.with_file_name("launches.log").display()
+        )).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&fake_wine, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        std::fs::write(install_dir.join("winepath.txt"), fake_wine.display().to_string()).unwrap();
+
+        TestEnvironment { root, install_dir, data_dir, config_dir, launch_log, mirrors }
+    }
+
+    /// Runs headless so failing tests do not sit through the pauses meant for people reading the console
+    pub fn command( &self, args: &[&str] ) -> Command {
+        let mut command = self.interactive_command(args);
+        command.arg("--headless");
+        command
+    }
+
+    pub fn interactive_command( &self, args: &[&str] ) -> Command {
+        let mut command = Command::new(bootstrapper_binary());
+        command.arg("--install-dir").arg(&self.install_dir);
+        command.args(args);
+        command.env("SYNTAX_MIRRORS", self.mirrors.join(","));
+        command.env("SYNTAX_BASE_URL", "www.syntax.test");
+        command.env("XDG_DATA_HOME", &self.data_dir);
+        command.env("XDG_CONFIG_HOME", &self.config_dir);
+        command.env_remove("SYNTAX_HOME");
+        command
+    }
+
+    pub fn run( &self, args: &[&str] ) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    pub fn version_directory( &self, version: &str ) -> PathBuf {
+        self.install_dir.join("Versions").join(version)
+    }
+
+    pub fn desktop_file( &self ) -> PathBuf {
+        self.data_dir.join("applications").join("syntax-player.desktop")
+    }
+
+    /// The arguments the fake wine was started with, one per line
+    pub fn launched_command_line( &self ) -> Vec<String> {
+        std::fs::read_to_string(&self.launch_log).unwrap().lines().map(str::to_string).collect()
+    }
+
+    /// Every client executable launched so far, oldest first
+    pub fn launched_clients( &self ) -> Vec<String> {
+        std::fs::read_to_string(self.launch_log.with_file_name("launches.log")).unwrap_or_default().lines().map(str::to_string).collect()
+    }
+}
+
+pub fn play_uri( client_year: &str ) -> String {
+    format!(
+        "syntax-player://1+launchmode:play+gameinfo:{}+placelauncherurl:https://www.syntax.test/Game/placelauncher.ashx?placeId=660&t={}+clientyear:{}",
+        TICKET, TICKET, client_year
+    )
+}
+
+pub fn assert_success( output: &Output ) {
+    assert!(
+        output.status.success(),
+        "bootstrapper exited with {:?}\nstdout:\n{}\nstderr:\n{}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+pub fn read( path: &Path ) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e))
+}
+
+/// An address nothing listens on, for mirrors that are down
+pub fn dead_mirror() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    address
+}
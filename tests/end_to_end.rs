@@ -0,0 +1,515 @@
+// End to end tests, the bootstrapper binary installs from a local mock setup server and launches a fake wine
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+
+#[test]
+fn fresh_install_creates_layout_and_launches_client() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_success(&output);
+
+    let version_directory = environment.version_directory(VERSION);
+    assert!(version_directory.join(BOOTSTRAPPER_FILENAME).is_file());
+    for client_year in CLIENT_YEARS {
+        let client_directory = version_directory.join(format!("Client{}", client_year));
+        assert_eq!(read(&client_directory.join("SyntaxPlayerBeta.exe")), format!("client {}", client_year));
+        assert!(client_directory.join("content").join("fonts.txt").is_file());
+    }
+    assert!(!environment.install_dir.join("Downloads").exists(), "downloads should be cleaned up after install");
+
+    let app_settings = read(&version_directory.join("AppSettings.xml"));
+    assert!(app_settings.contains("<ContentFolder>content</ContentFolder>"));
+    assert!(app_settings.contains("<BaseUrl>http://www.syntax.test</BaseUrl>"));
+
+    let desktop_file = read(&environment.desktop_file());
+    assert!(desktop_file.contains(&format!("Exec=\"{}\" %u", version_directory.join(BOOTSTRAPPER_FILENAME).display())));
+    assert!(desktop_file.contains("MimeType=x-scheme-handler/syntax-player;"));
+
+    assert_eq!(environment.launched_command_line(), vec![
+        version_directory.join("Client2018").join("SyntaxPlayerBeta.exe").display().to_string(),
+        "--play".to_string(),
+        "--authenticationUrl".to_string(),
+        "https://www.syntax.test/Login/Negotiate.ashx".to_string(),
+        "--authenticationTicket".to_string(),
+        TICKET.to_string(),
+        "--joinScriptUrl".to_string(),
+        format!("https://www.syntax.test/Game/placelauncher.ashx?placeId=660&t={}", TICKET),
+    ]);
+}
+
+#[test]
+fn unknown_client_year_launches_2016_client() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("1999")]));
+    assert_eq!(
+        environment.launched_command_line()[0],
+        environment.version_directory(VERSION).join("Client2016").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+}
+
+#[test]
+fn installed_version_is_not_downloaded_again() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    let downloads_after_install = server.requests().iter().filter(|path| path.ends_with(".zip")).count();
+    assert_eq!(downloads_after_install, CLIENT_YEARS.len());
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    let downloads_after_launch = server.requests().iter().filter(|path| path.ends_with(".zip")).count();
+    assert_eq!(downloads_after_launch, downloads_after_install);
+}
+
+#[test]
+fn new_version_replaces_old_version() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    let old_version_directory = environment.version_directory("version-00000000000000ff");
+    std::fs::create_dir_all(old_version_directory.join("Client2018")).unwrap();
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    assert!(!old_version_directory.exists(), "old versions should be removed after installing a new one");
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").is_file());
+}
+
+#[test]
+fn failed_mirror_falls_back_to_next_mirror() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![dead_mirror(), server.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").is_file());
+}
+
+
+#[test]
+fn missing_artifact_on_first_mirror_is_fetched_from_second() {
+    let primary = MockSetupServer::start_with_release(VERSION);
+    let secondary = MockSetupServer::start_with_release(VERSION);
+    primary.remove(&format!("/{}-2014client.zip", VERSION));
+    let environment = TestEnvironment::new(vec![primary.address.clone(), secondary.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2014")]));
+    assert!(secondary.requests().contains(&format!("/{}-2014client.zip", VERSION)));
+    assert_eq!(read(&environment.version_directory(VERSION).join("Client2014").join("SyntaxPlayerBeta.exe")), "client 2014");
+}
+
+#[test]
+fn chunked_downloads_without_content_length_install() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    server.set_chunked(true);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    assert_eq!(read(&environment.version_directory(VERSION).join("Client2018").join("SyntaxPlayerBeta.exe")), "client 2018");
+}
+
+#[test]
+fn mirror_rejecting_head_still_serves_downloads() {
+    let cdn = MockSetupServer::start_with_release(VERSION);
+    cdn.set_reject_head(true);
+    let fallback = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![cdn.address.clone(), fallback.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    let zip_downloads = |server: &MockSetupServer| server.requests().iter().filter(|path| path.ends_with("client.zip")).count();
+    assert_eq!(zip_downloads(&cdn), CLIENT_YEARS.len(), "the mirror was demoted for rejecting HEAD");
+    assert_eq!(zip_downloads(&fallback), 0);
+}
+
+#[test]
+fn not_enough_disk_space_keeps_current_files() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    // A broken install is repaired, but the zips claim to need more space than any disk has
+    std::fs::remove_file(environment.version_directory(VERSION).join("AppSettings.xml")).unwrap();
+    for client_year in CLIENT_YEARS {
+        server.set_head_length(&format!("/{}-{}client.zip", VERSION, client_year), 1 << 50);
+    }
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Not enough disk space to install"));
+    assert!(environment.version_directory(VERSION).join("Client2018").join("SyntaxPlayerBeta.exe").is_file());
+}
+
+#[test]
+fn failed_repair_download_keeps_current_files() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    // A broken install is repaired, but the last zip can not be downloaded
+    std::fs::remove_file(environment.version_directory(VERSION).join("AppSettings.xml")).unwrap();
+    server.remove(&format!("/{}-2021client.zip", VERSION));
+    let output = environment.run(&[&play_uri("2018")]);
+    assert!(!output.status.success());
+    for client_year in CLIENT_YEARS {
+        assert!(environment.version_directory(VERSION).join(format!("Client{}", client_year)).join("SyntaxPlayerBeta.exe").is_file());
+    }
+}
+
+#[test]
+fn version_response_is_trimmed_and_keeps_its_case() {
+    // The mock server is case sensitive like S3, so every download has to use the version as it was sent
+    let version = "version-0123456789ABCdef";
+    let server = MockSetupServer::start();
+    server.serve_release(version);
+    server.serve("/version", format!("  {}\r\n", version).into_bytes());
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    assert!(environment.version_directory(version).join("AppSettings.xml").is_file());
+    assert!(server.requests().contains(&format!("/{}-2018client.zip", version)));
+}
+
+#[test]
+fn invalid_version_response_falls_back_to_next_mirror() {
+    let broken = MockSetupServer::start();
+    broken.serve("/version", b"<html><body>502 Bad Gateway</body></html>".to_vec());
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![broken.address.clone(), server.address.clone()]);
+
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("returned an invalid response"));
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").is_file());
+    let versions: Vec<_> = std::fs::read_dir(environment.install_dir.join("Versions")).unwrap().collect();
+    assert_eq!(versions.len(), 1);
+}
+
+#[test]
+fn invalid_version_response_from_every_mirror_is_a_network_error() {
+    let server = MockSetupServer::start();
+    server.serve("/version", b"../../escape".to_vec());
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("expected a version like"));
+    assert!(!environment.root.path().join("escape").exists());
+}
+
+#[test]
+fn headless_failure_exits_without_waiting() {
+    let environment = TestEnvironment::new(vec![dead_mirror()]);
+
+    let started = std::time::Instant::now();
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5), "headless run waited {:?} before exiting", started.elapsed());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Build Date"), "headless run printed the banner");
+}
+
+#[test]
+fn interactive_run_prints_banner() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.interactive_command(&[&play_uri("2018")]).output().unwrap();
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Build Date"));
+}
+
+#[test]
+fn long_base_url_falls_back_to_short_banner() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    let base_url = "staging.selfhosted-revival.example.org/with/a/path/long/enough/to/overflow/the/banner";
+
+    // stdout is piped, so the banner is laid out for 80 columns
+    let output = environment.interactive_command(&["clean"]).env("SYNTAX_BASE_URL", base_url).output().unwrap();
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("SYNTAX Bootstrapper | {}", base_url)), "stdout:\n{}", stdout);
+}
+
+#[test]
+fn unknown_config_keys_are_only_warned_about() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    // Written by a newer bootstrapper, this one still has to start
+    std::fs::write(
+        environment.install_dir.join("config.toml"),
+        "[behaviour]\nclear_screen = false\nopen_landing_page = false\nsparkles = true\n\n[network]\nlimit_rate = \"1G\"\n\n[telemetry]\nenabled = false\n"
+    ).unwrap();
+
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Ignoring unknown config key behaviour.sparkles"), "{}", stdout);
+    assert!(stdout.contains("Ignoring unknown config key telemetry"), "{}", stdout);
+    assert!(!stdout.contains("config key network"), "{}", stdout);
+    assert!(!environment.launched_command_line().is_empty());
+}
+
+#[test]
+fn supervised_client_exit_is_logged() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.run(&["--supervise", &play_uri("2018")]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("SYNTAX exited with code 0"));
+}
+
+#[test]
+fn supervised_early_crash_is_a_launch_error() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.command(&["--supervise", &play_uri("2018")]).env("FAKE_WINE_EXIT", "3").output().unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("SYNTAX exited with code 3"));
+    assert!(stdout.contains("crashed"));
+}
+
+#[test]
+fn client_crash_writes_redacted_crash_report() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    let client_logs = environment.version_directory(VERSION).join("Client2018").join("logs");
+    std::fs::create_dir_all(&client_logs).unwrap();
+    std::fs::write(client_logs.join("client.log"), format!("joining with t={}\nrender device lost\n", TICKET)).unwrap();
+
+    let output = environment.command(&["--supervise", &play_uri("2018")]).env("FAKE_WINE_EXIT", "3").output().unwrap();
+    assert_eq!(output.status.code(), Some(5));
+
+    let crash_reports: Vec<_> = std::fs::read_dir(environment.install_dir.join("CrashReports")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(crash_reports.len(), 1);
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&crash_reports[0]).unwrap()).unwrap();
+    let mut read_entry = |name: &str| {
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut zip.by_name(name).unwrap_or_else(|_| panic!("{} is missing from the crash report", name)), &mut contents).unwrap();
+        contents
+    };
+
+    let report = read_entry("report.txt");
+    assert!(report.contains(&format!("Client version: {}", VERSION)));
+    assert!(report.contains("Exit code: 3"));
+    assert!(report.contains("wine-9.0-fake"));
+    assert!(report.contains("--authenticationTicket [REDACTED]"));
+    assert!(read_entry("bootstrapper.log").contains("Launching SYNTAX"));
+    let client_log = read_entry("client/logs/client.log");
+    assert!(client_log.contains("render device lost"));
+
+    for contents in [report, client_log] {
+        assert!(!contents.contains(TICKET), "ticket leaked into the crash report");
+    }
+}
+
+#[test]
+fn unsupervised_client_crash_writes_crash_report() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    // Without --supervise the launch still succeeds, linux waits for the client anyway
+    let output = environment.command(&[&play_uri("2018")]).env("FAKE_WINE_EXIT", "3").output().unwrap();
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Crash report saved to"));
+    let crash_reports: Vec<_> = std::fs::read_dir(environment.install_dir.join("CrashReports")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(crash_reports.len(), 1);
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&crash_reports[0]).unwrap()).unwrap();
+    let mut report = String::new();
+    std::io::Read::read_to_string(&mut zip.by_name("report.txt").unwrap(), &mut report).unwrap();
+    assert!(report.contains("Exit code: 3"));
+}
+
+#[test]
+fn second_instance_waits_for_install_lock() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    // Stand in for a first bootstrapper that is still installing
+    let lock_file = std::fs::File::create(environment.install_dir.join("install.lock")).unwrap();
+    lock_file.lock().unwrap();
+
+    let mut child = environment.command(&[&play_uri("2018")])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    assert!(child.try_wait().unwrap().is_none(), "bootstrapper did not wait for the install lock");
+    assert!(!environment.launch_log.exists(), "client launched while another bootstrapper held the lock");
+
+    drop(lock_file);
+    let output = child.wait_with_output().unwrap();
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Another SYNTAX bootstrapper is installing right now"));
+    assert_eq!(
+        environment.launched_command_line()[0],
+        environment.version_directory(VERSION).join("Client2018").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+}
+
+// Starts a bootstrapper and waits until it is installing and listening for launch requests
+fn start_installing( environment: &TestEnvironment, args: &[&str] ) -> std::process::Child {
+    let installing = environment.command(args)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let socket_path = environment.install_dir.join("bootstrapper.sock");
+    let started = std::time::Instant::now();
+    while !socket_path.exists() {
+        assert!(started.elapsed() < std::time::Duration::from_secs(30), "the first bootstrapper never started installing");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    installing
+}
+
+fn client_executable( environment: &TestEnvironment, client_year: &str ) -> String {
+    environment.version_directory(VERSION).join(format!("Client{}", client_year)).join("SyntaxPlayerBeta.exe").display().to_string()
+}
+
+#[test]
+fn relaunch_pause_does_not_hold_install_lock() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    // A different latest bootstrapper, so this one asks to be launched from the website again
+    server.serve(&format!("/{}-{}", VERSION, BOOTSTRAPPER_FILENAME), b"#!/bin/sh\n".to_vec());
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let mut relaunching = environment.interactive_command(&[&play_uri("2018")])
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let latest_bootstrapper = environment.version_directory(VERSION).join(BOOTSTRAPPER_FILENAME).display().to_string();
+    let started = std::time::Instant::now();
+    while !std::fs::read_to_string(environment.desktop_file()).unwrap_or_default().contains(&latest_bootstrapper) {
+        assert!(started.elapsed() < std::time::Duration::from_secs(30), "the bootstrapper never pointed the scheme at the latest bootstrapper");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    // Launching from the website during the 20 second pause does not wait for the install lock
+    let started = std::time::Instant::now();
+    assert_success(&environment.run(&["clean"]));
+    assert!(started.elapsed() < std::time::Duration::from_secs(10), "waited {:?} for the install lock", started.elapsed());
+    assert!(relaunching.try_wait().unwrap().is_none(), "the bootstrapper did not pause");
+    relaunching.kill().unwrap();
+    relaunching.wait().unwrap();
+}
+
+#[test]
+fn launch_request_is_forwarded_to_installing_bootstrapper() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    server.set_delay(std::time::Duration::from_millis(500));
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    // Started without a uri, so the forwarded one is launched by the installing bootstrapper
+    let installing = start_installing(&environment, &[]);
+    let forwarding = environment.run(&[&play_uri("2014")]);
+    assert_success(&forwarding);
+    let stdout = String::from_utf8_lossy(&forwarding.stdout);
+    assert!(stdout.contains("it will launch the game once it is done"), "{}", stdout);
+    assert!(stdout.contains("is launching the game"), "{}", stdout);
+
+    let installing = installing.wait_with_output().unwrap();
+    assert_success(&installing);
+    assert!(String::from_utf8_lossy(&installing.stdout).contains("Received a launch request from another bootstrapper"));
+    assert_eq!(environment.launched_clients(), vec![client_executable(&environment, "2014")]);
+    assert!(!environment.install_dir.join("bootstrapper.sock").exists());
+}
+
+#[test]
+fn launch_requests_not_launched_by_installing_bootstrapper_fall_back() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    server.set_delay(std::time::Duration::from_millis(500));
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    // The installing bootstrapper launches its own uri, the forwarded ones are handed back and launched by their senders
+    let installing = start_installing(&environment, &[&play_uri("2018")]);
+    let older = environment.command(&[&play_uri("2014")])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    let newer = environment.run(&[&play_uri("2016")]);
+    let older = older.wait_with_output().unwrap();
+
+    for forwarding in [&older, &newer] {
+        assert_success(forwarding);
+        let stdout = String::from_utf8_lossy(&forwarding.stdout);
+        assert!(stdout.contains("it will launch the game once it is done"), "the request was never queued:\n{}", stdout);
+        assert!(stdout.contains("did not launch the game, launching it from here instead"), "{}", stdout);
+    }
+    let installing = installing.wait_with_output().unwrap();
+    assert_success(&installing);
+    assert!(String::from_utf8_lossy(&installing.stdout).contains("Handing 2 launch requests back"));
+
+    let mut launched_clients = environment.launched_clients();
+    launched_clients.sort();
+    let mut expected_clients = vec![client_executable(&environment, "2014"), client_executable(&environment, "2016"), client_executable(&environment, "2018")];
+    expected_clients.sort();
+    assert_eq!(launched_clients, expected_clients);
+}
+
+#[test]
+fn forwarded_launch_request_survives_installing_bootstrapper_dying() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    server.set_delay(std::time::Duration::from_millis(500));
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let mut installing = environment.command(&[&play_uri("2018")])
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let socket_path = environment.install_dir.join("bootstrapper.sock");
+    let started = std::time::Instant::now();
+    while !socket_path.exists() {
+        assert!(started.elapsed() < std::time::Duration::from_secs(30), "the first bootstrapper never started installing");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    let forwarding = environment.command(&[&play_uri("2014")])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Give the request time to be queued, then the first bootstrapper dies before it is done installing
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    installing.kill().unwrap();
+    installing.wait().unwrap();
+
+    let forwarding = forwarding.wait_with_output().unwrap();
+    assert_success(&forwarding);
+    let stdout = String::from_utf8_lossy(&forwarding.stdout);
+    assert!(stdout.contains("it will launch the game once it is done"), "the request was never queued:\n{}", stdout);
+    assert!(stdout.contains("did not launch the game"), "{}", stdout);
+    assert_eq!(
+        environment.launched_command_line()[0],
+        environment.version_directory(VERSION).join("Client2014").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+}
+
+#[test]
+fn unreachable_setup_servers_launch_installed_version() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    std::fs::remove_file(&environment.launch_log).unwrap();
+
+    let offline = TestEnvironment { mirrors: vec![dead_mirror()], ..environment };
+    let output = offline.run(&[&play_uri("2020")]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("Launching the installed version {} offline", VERSION)));
+    assert_eq!(
+        offline.launched_command_line()[0],
+        offline.version_directory(VERSION).join("Client2020").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+}
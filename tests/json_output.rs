@@ -0,0 +1,111 @@
+// --output json, every line on stdout must be a json event
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+use serde_json::Value;
+
+fn events( output: &std::process::Output ) -> Vec<Value> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("stdout line is not json ({}): {}", e, line)))
+        .collect()
+}
+
+fn events_named<'a>( events: &'a [Value], name: &str ) -> Vec<&'a Value> {
+    events.iter().filter(|event| event["event"] == name).collect()
+}
+
+#[test]
+fn install_and_launch_are_reported_as_json_events() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.run(&["--output", "json", &play_uri("2018")]);
+    assert_success(&output);
+    let events = events(&output);
+
+    let stages: Vec<(String, String)> = events.iter()
+        .filter(|event| event["event"] == "stage_started" || event["event"] == "stage_finished")
+        .map(|event| (event["event"].as_str().unwrap().to_string(), event["stage"].as_str().unwrap().to_string()))
+        .collect();
+    let expected: Vec<(String, String)> = [
+        ("stage_started", "update"), ("stage_finished", "update"),
+        ("stage_started", "install"), ("stage_finished", "install"),
+        ("stage_started", "launch"), ("stage_finished", "launch"),
+    ].iter().map(|(event, stage)| (event.to_string(), stage.to_string())).collect();
+    assert_eq!(stages, expected);
+    assert_eq!(events_named(&events, "stage_finished")[0]["version"], VERSION);
+
+    let finished_downloads = events_named(&events, "download_progress").into_iter()
+        .filter(|event| event["finished"] == true)
+        .count();
+    assert_eq!(finished_downloads, CLIENT_YEARS.len() + 1, "every client zip and the bootstrapper should be downloaded");
+    assert!(!events_named(&events, "log").is_empty());
+
+    let launched = events_named(&events, "launched");
+    assert_eq!(launched.len(), 1);
+    let command: Vec<&str> = launched[0]["command"].as_array().unwrap().iter().map(|arg| arg.as_str().unwrap()).collect();
+    assert!(command.iter().any(|arg| arg.ends_with("Client2018/SyntaxPlayerBeta.exe")));
+    assert!(command.contains(&"--authenticationTicket"));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains(TICKET), "ticket leaked into json output");
+    assert!(!stdout.contains('\x1b'), "json output should not contain color codes");
+}
+
+#[test]
+fn client_output_is_forwarded_as_log_events() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.command(&["--output", "json", &play_uri("2018")])
+        .env("FAKE_WINE_OUTPUT", "fixme:d3d:wined3d_guess_card No card found")
+        .output()
+        .unwrap();
+    assert_success(&output);
+    let events = events(&output);
+    assert!(
+        events_named(&events, "log").iter().any(|event| event["message"].as_str().unwrap().contains("fixme:d3d:wined3d_guess_card")),
+        "the client output should arrive as a log event"
+    );
+}
+
+#[test]
+fn errors_are_reported_with_exit_code() {
+    let environment = TestEnvironment::new(vec![dead_mirror()]);
+
+    let output = environment.run(&["--output=json", &play_uri("2018")]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let events = events(&output);
+    let errors = events_named(&events, "error");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["code"], 2);
+    assert_eq!(errors[0]["category"], "network");
+}
+
+#[test]
+fn logs_command_is_reported_as_json_event() {
+    let environment = TestEnvironment::new(Vec::new());
+    let logs_directory = environment.install_dir.join("Logs");
+    std::fs::create_dir_all(&logs_directory).unwrap();
+    std::fs::write(logs_directory.join("bootstrapper-20200101-000000-000.log"), "first run").unwrap();
+
+    let output = environment.run(&["--output", "json", "logs"]);
+    assert_success(&output);
+    let logs_events = events(&output);
+    let logs = events_named(&logs_events, "logs");
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0]["directory"], logs_directory.display().to_string());
+    assert_eq!(logs[0]["latest"], logs_directory.join("bootstrapper-20200101-000000-000.log").display().to_string());
+    assert!(logs[0].get("bundle").is_none());
+
+    let output = environment.run(&["--output", "json", "logs", "--bundle"]);
+    assert_success(&output);
+    let bundle_events = events(&output);
+    let logs = events_named(&bundle_events, "logs");
+    assert_eq!(logs.len(), 1);
+    assert!(std::path::Path::new(logs[0]["bundle"].as_str().unwrap()).is_file());
+}
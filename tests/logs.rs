@@ -0,0 +1,170 @@
+// Log files written to Syntax/Logs and the logs subcommand
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+
+fn log_files( environment: &TestEnvironment ) -> Vec<std::path::PathBuf> {
+    let mut log_files: Vec<_> = std::fs::read_dir(environment.install_dir.join("Logs")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|extension| extension == "log").unwrap_or(false))
+        .collect();
+    log_files.sort();
+    log_files
+}
+
+#[test]
+fn run_writes_log_file_without_colors() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    let log_files = log_files(&environment);
+    assert_eq!(log_files.len(), 1);
+    let file_name = log_files[0].file_name().unwrap().to_str().unwrap().to_string();
+    assert!(file_name.starts_with("bootstrapper-"), "unexpected log file name {}", file_name);
+
+    let log = read(&log_files[0]);
+    assert!(log.contains(&format!("Latest Client Version: {}", VERSION)));
+    assert!(log.contains("Launching SYNTAX"));
+    assert!(!log.contains('\x1b'), "log file should not contain color codes");
+}
+
+#[test]
+fn old_log_files_are_rotated() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    std::fs::write(environment.install_dir.join("config.toml"), "[behaviour]\nclear_screen = false\n\n[logging]\nkeep_files = 2\n").unwrap();
+
+    let logs_directory = environment.install_dir.join("Logs");
+    std::fs::create_dir_all(&logs_directory).unwrap();
+    for old_log in ["bootstrapper-20200101-000000-000.log", "bootstrapper-20200102-000000-000.log", "bootstrapper-20200103-000000-000.log"] {
+        std::fs::write(logs_directory.join(old_log), "old").unwrap();
+    }
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    let log_files = log_files(&environment);
+    assert_eq!(log_files.len(), 2);
+    assert_eq!(log_files[0].file_name().unwrap(), "bootstrapper-20200103-000000-000.log");
+}
+
+#[test]
+fn prefetch_runs_do_not_rotate_out_launch_logs() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    std::fs::write(environment.install_dir.join("config.toml"), "[behaviour]\nclear_screen = false\n\n[logging]\nkeep_files = 2\n").unwrap();
+
+    let logs_directory = environment.install_dir.join("Logs");
+    std::fs::create_dir_all(&logs_directory).unwrap();
+    for old_log in ["bootstrapper-20200101-000000-000.log", "bootstrapper-20200102-000000-000.log"] {
+        std::fs::write(logs_directory.join(old_log), "old").unwrap();
+    }
+
+    for _ in 0..3 {
+        assert_success(&environment.run(&["prefetch"]));
+    }
+
+    let file_names: Vec<String> = log_files(&environment).iter().map(|path| path.file_name().unwrap().to_str().unwrap().to_string()).collect();
+    assert_eq!(&file_names[..2], ["bootstrapper-20200101-000000-000.log", "bootstrapper-20200102-000000-000.log"]);
+    assert_eq!(file_names.len(), 4, "{:?}", file_names);
+    assert!(file_names[2..].iter().all(|file_name| file_name.starts_with("prefetch-")), "{:?}", file_names);
+}
+
+#[test]
+fn logs_command_prints_directory_and_bundles_logs() {
+    let environment = TestEnvironment::new(Vec::new());
+    let logs_directory = environment.install_dir.join("Logs");
+    std::fs::create_dir_all(&logs_directory).unwrap();
+    std::fs::write(logs_directory.join("bootstrapper-20200101-000000-000.log"), "first run").unwrap();
+
+    let output = environment.run(&["logs"]);
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&logs_directory.display().to_string()));
+    assert!(stdout.contains("bootstrapper-20200101-000000-000.log"));
+
+    assert_success(&environment.run(&["logs", "--bundle"]));
+    let bundle = std::fs::read_dir(&logs_directory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().map(|extension| extension == "zip").unwrap_or(false))
+        .expect("logs --bundle should create a zip");
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(bundle).unwrap()).unwrap();
+    let mut contents = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("bootstrapper-20200101-000000-000.log").unwrap(), &mut contents).unwrap();
+    assert_eq!(contents, "first run");
+}
+
+#[test]
+fn syntax_log_environment_variable_filters_console_output() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.command(&[&play_uri("2018")]).env("SYNTAX_LOG", "warn").output().unwrap();
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("INFO"), "info messages should be hidden at the warn level:\n{}", stdout);
+    assert!(!stdout.contains("DEBUG"));
+}
+
+#[test]
+fn verbose_flag_overrides_config_level() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    std::fs::write(environment.install_dir.join("config.toml"), "[behaviour]\nclear_screen = false\n\n[logging]\nlevel = \"error\"\n").unwrap();
+
+    let quiet = environment.run(&[&play_uri("2018")]);
+    assert_success(&quiet);
+    assert!(!String::from_utf8_lossy(&quiet.stdout).contains("INFO"));
+
+    let verbose = environment.command(&["--verbose", &play_uri("2018")]).env("SYNTAX_LOG", "error").output().unwrap();
+    assert_success(&verbose);
+    let stdout = String::from_utf8_lossy(&verbose.stdout);
+    assert!(stdout.contains("DEBUG"), "--verbose should show debug messages:\n{}", stdout);
+    assert!(!stdout.contains("TRACE"));
+}
+
+#[test]
+fn short_verbose_flags_are_parsed() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.run(&["-vvv", &play_uri("2018")]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("TRACE"));
+
+    for unknown in ["-q", "-vq", "-"] {
+        let output = environment.run(&[unknown, &play_uri("2018")]);
+        assert_eq!(output.status.code(), Some(7), "{} was accepted", unknown);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("Unknown argument"));
+    }
+}
+
+#[test]
+fn invalid_syntax_log_is_a_config_error() {
+    let environment = TestEnvironment::new(Vec::new());
+    let output = environment.command(&["logs"]).env("SYNTAX_LOG", "loud").output().unwrap();
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn authentication_tickets_are_redacted_from_output_and_log_file() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.command(&["--verbose", "--verbose", &play_uri("2018")]).output().unwrap();
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains(TICKET), "ticket leaked to the console:\n{}", stdout);
+    assert!(stdout.contains("[REDACTED]"));
+
+    let log = read(&log_files(&environment)[0]);
+    assert!(!log.contains(TICKET), "ticket leaked to the log file:\n{}", log);
+    assert!(log.contains("gameinfo:[REDACTED]"));
+    assert!(log.contains("placeId=660&t=[REDACTED]"));
+
+    // The client itself still gets the real ticket
+    assert!(environment.launched_command_line().contains(&TICKET.to_string()));
+}
@@ -0,0 +1,155 @@
+// The [network] config, every run goes through the mock setup server
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+use std::time::{Duration, Instant};
+use syntax_bootstrapper::config::NetworkConfig;
+use syntax_bootstrapper::http::{build_client, download_file};
+
+const USER_CONFIG: &str = "[behaviour]\nclear_screen = false\nopen_landing_page = false\n";
+
+#[test]
+fn requests_carry_bootstrapper_user_agent() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    let expected = format!("SyntaxBootstrapper/{} (linux)", env!("CARGO_PKG_VERSION"));
+    let user_agents = server.user_agents();
+    assert!(!user_agents.is_empty());
+    assert!(user_agents.iter().all(|user_agent| user_agent == &expected), "unexpected user agents {:?}", user_agents);
+}
+
+#[test]
+fn requests_go_through_configured_proxy() {
+    let proxy = MockSetupServer::start_with_release(VERSION);
+    // Only reachable through the proxy
+    let environment = TestEnvironment::new(vec!["http://setup.syntax.invalid".to_string()]);
+
+    let output = environment.command(&[&play_uri("2018")]).env("SYNTAX_PROXY", &proxy.address).output().unwrap();
+    assert_success(&output);
+    assert!(proxy.requests().contains(&"/version".to_string()));
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").is_file());
+}
+
+#[test]
+fn stalled_mirror_times_out_and_falls_back() {
+    let stalled = MockSetupServer::start_with_release(VERSION);
+    stalled.set_delay(Duration::from_secs(30));
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![stalled.address.clone(), server.address.clone()]);
+    std::fs::write(environment.install_dir.join("config.toml"), format!("{}\n[network]\nread_timeout_secs = 1\n", USER_CONFIG)).unwrap();
+
+    let started = Instant::now();
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_success(&output);
+    assert!(started.elapsed() < Duration::from_secs(20), "the stalled mirror was waited on for {:?}", started.elapsed());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("did not send anything for 1 seconds"));
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").is_file());
+}
+
+#[test]
+fn https_mirror_is_trusted_with_configured_ca_certificate() {
+    let server = MockSetupServer::start_https();
+    server.serve_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    // The self-signed certificate is not trusted by default
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(server.requests().is_empty());
+
+    let ca_certificate_path = environment.root.path().join("mock-ca.pem");
+    std::fs::write(&ca_certificate_path, server.certificate.as_ref().unwrap()).unwrap();
+    std::fs::write(environment.install_dir.join("config.toml"), format!("{}\n[network]\nca_certificate = \"{}\"\n", USER_CONFIG, ca_certificate_path.display())).unwrap();
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    assert!(server.requests().contains(&format!("/{}-2018client.zip", VERSION)));
+    assert_eq!(
+        environment.launched_command_line()[0],
+        environment.version_directory(VERSION).join("Client2018").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+}
+
+#[test]
+fn missing_ca_certificate_is_a_config_error() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    let ca_certificate_path = environment.root.path().join("missing-ca.pem");
+    std::fs::write(environment.install_dir.join("config.toml"), format!("{}\n[network]\nca_certificate = \"{}\"\n", USER_CONFIG, ca_certificate_path.display())).unwrap();
+
+    let output = environment.run(&[&play_uri("2018")]);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Failed to read the CA certificate"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn proxy_password_is_not_logged() {
+    let proxy = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec!["http://setup.syntax.invalid".to_string()]);
+    let proxy_url = proxy.address.replace("http://", "http://player:hunter2@");
+
+    let output = environment.command(&["-vv", &play_uri("2018")]).env("SYNTAX_PROXY", &proxy_url).output().unwrap();
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Using proxy"));
+    assert!(!stdout.contains("hunter2"), "the proxy password was logged");
+    let logs = std::fs::read_dir(environment.install_dir.join("Logs")).unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap_or_default())
+        .collect::<String>();
+    assert!(!logs.contains("hunter2"), "the proxy password was written to the log file");
+}
+
+#[test]
+fn limit_rate_slows_downloads_down() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    // The bootstrapper is already downloaded, so only the client zips are downloaded again
+    std::fs::remove_file(environment.version_directory(VERSION).join("AppSettings.xml")).unwrap();
+    let client_zip_bytes: usize = CLIENT_YEARS.iter().map(|client_year| client_zip(client_year).len()).sum();
+
+    let started = Instant::now();
+    let output = environment.command(&["--limit-rate", "1K", &play_uri("2018")])
+        .env_remove("SYNTAX_LIMIT_RATE")
+        .output()
+        .unwrap();
+    let elapsed = started.elapsed();
+    assert_success(&output);
+    assert!(
+        elapsed >= Duration::from_secs_f64(client_zip_bytes as f64 / 1024.0 * 0.8),
+        "{} bytes were downloaded in {:?} despite the 1K/s limit", client_zip_bytes, elapsed
+    );
+}
+
+#[test]
+fn invalid_limit_rate_is_rejected() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+
+    let output = environment.run(&["--limit-rate", "fast", &play_uri("2018")]);
+    assert_eq!(output.status.code(), Some(7));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Invalid download rate"));
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn clients_keep_their_own_rate_limit() {
+    let server = MockSetupServer::start();
+    server.serve("/payload", vec![0; 8192]);
+    let url = format!("{}/payload", server.address);
+    let downloads = tempfile::tempdir().unwrap();
+    let limited = build_client(&NetworkConfig { limit_rate: Some("4K".to_string()), ..NetworkConfig::default() }).unwrap();
+    // Built last, so a limit shared by the whole process would be lifted for both
+    let unlimited = build_client(&NetworkConfig::default()).unwrap();
+
+    let started = Instant::now();
+    download_file(&unlimited, &url, &downloads.path().join("unlimited")).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1), "the unlimited client took {:?}", started.elapsed());
+    let started = Instant::now();
+    download_file(&limited, &url, &downloads.path().join("limited")).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(1500), "8K were downloaded in {:?} despite the 4K/s limit", started.elapsed());
+}
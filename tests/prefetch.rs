@@ -0,0 +1,165 @@
+// prefetch stages the next version in the background and the next launch switches to it
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+use std::time::Duration;
+
+const NEXT_VERSION: &str = "version-fedcba9876543210";
+
+fn client_zip_requests( server: &MockSetupServer ) -> usize {
+    server.requests().iter().filter(|path| path.ends_with("client.zip")).count()
+}
+
+#[test]
+fn prefetch_stages_next_version_without_touching_current_one() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+
+    server.serve_release(NEXT_VERSION);
+    server.serve("/version", NEXT_VERSION.as_bytes().to_vec());
+    assert_success(&environment.run(&["prefetch"]));
+
+    assert!(environment.version_directory(VERSION).join("Client2018").join("SyntaxPlayerBeta.exe").exists(), "the current version was removed by prefetch");
+    let next_version_directory = environment.version_directory(NEXT_VERSION);
+    assert!(next_version_directory.join("AppSettings.xml").exists());
+    assert!(next_version_directory.join(BOOTSTRAPPER_FILENAME).exists());
+    assert_eq!(read(&next_version_directory.join("Client2014").join("SyntaxPlayerBeta.exe")), "client 2014");
+
+    // The next launch uses the prefetched files instead of downloading them again
+    let client_zips_before_launch = client_zip_requests(&server);
+    assert_success(&environment.run(&[&play_uri("2014")]));
+    assert_eq!(client_zip_requests(&server), client_zips_before_launch);
+    assert!(!environment.version_directory(VERSION).exists(), "the old version should be removed once the prefetched one is used");
+    assert!(!next_version_directory.join("staged").exists());
+    assert_eq!(
+        environment.launched_command_line()[0],
+        next_version_directory.join("Client2014").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+}
+
+#[test]
+fn offline_launch_ignores_prefetched_version() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    server.serve_release(NEXT_VERSION);
+    assert_success(&environment.run(&["prefetch"]));
+
+    let offline = TestEnvironment { mirrors: vec![dead_mirror()], ..environment };
+    let output = offline.run(&[&play_uri("2020")]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("Launching the installed version {} offline", VERSION)));
+    assert_eq!(
+        offline.launched_command_line()[0],
+        offline.version_directory(VERSION).join("Client2020").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+    // Nothing was switched over, the next online launch does that
+    assert!(offline.version_directory(NEXT_VERSION).join("staged").exists());
+    assert!(read(&offline.desktop_file()).contains(&format!("Exec=\"{}\" %u", offline.version_directory(VERSION).join(BOOTSTRAPPER_FILENAME).display())));
+}
+
+#[test]
+fn launch_does_not_wait_for_running_prefetch() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    std::fs::remove_file(&environment.launch_log).unwrap();
+
+    // Every request takes a while, so the prefetch is still downloading when play is clicked
+    server.serve_release(NEXT_VERSION);
+    server.set_delay(Duration::from_secs(2));
+    let mut prefetch = environment.command(&["prefetch"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let staging_lock = std::fs::OpenOptions::new().create(true).truncate(false).write(true).open(environment.install_dir.join("staging.lock")).unwrap();
+    for _ in 0..100 {
+        match staging_lock.try_lock() {
+            Ok(()) => staging_lock.unlock().unwrap(),
+            Err(_) => break,
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let output = environment.run(&[&play_uri("2020")]);
+    assert_success(&output);
+    assert!(prefetch.try_wait().unwrap().is_none(), "the launch waited for the prefetch to finish");
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("launching the installed version {}", VERSION)));
+    assert_eq!(
+        environment.launched_command_line()[0],
+        environment.version_directory(VERSION).join("Client2020").join("SyntaxPlayerBeta.exe").display().to_string()
+    );
+
+    assert_success(&prefetch.wait_with_output().unwrap());
+    assert!(environment.version_directory(NEXT_VERSION).join("staged").exists());
+    assert!(environment.version_directory(VERSION).join("AppSettings.xml").exists());
+}
+
+#[test]
+fn prefetch_does_nothing_when_up_to_date() {
+    let server = MockSetupServer::start_with_release(VERSION);
+    let environment = TestEnvironment::new(vec![server.address.clone()]);
+    assert_success(&environment.run(&[&play_uri("2018")]));
+    let client_zips_after_install = client_zip_requests(&server);
+
+    let output = environment.run(&["prefetch"]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("nothing to prefetch"));
+    assert_eq!(client_zip_requests(&server), client_zips_after_install);
+}
+
+// A fake systemctl keeps the tests away from the real user session, returns its directory and the log of its calls
+fn fake_systemctl( environment: &TestEnvironment ) -> (std::path::PathBuf, std::path::PathBuf) {
+    let bin_directory = environment.root.path().join("bin");
+    std::fs::create_dir_all(&bin_directory).unwrap();
+    let systemctl_log = environment.root.path().join("systemctl.log");
+    std::fs::write(bin_directory.join("systemctl"), format!("#!/bin/sh\necho \"$@\" >> \"{}\"\n", systemctl_log.display())).unwrap();
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(bin_directory.join("systemctl"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    (bin_directory, systemctl_log)
+}
+
+#[test]
+fn install_schedule_writes_systemd_timer() {
+    let environment = TestEnvironment::new(Vec::new());
+    std::fs::write(environment.install_dir.join("config.toml"), "[prefetch]\ninterval_hours = 3\n").unwrap();
+
+    let (bin_directory, systemctl_log) = fake_systemctl(&environment);
+
+    let output = environment.command(&["prefetch", "--install-schedule"]).env("PATH", &bin_directory).output().unwrap();
+    assert_success(&output);
+
+    let unit_directory = environment.config_dir.join("systemd").join("user");
+    let service = read(&unit_directory.join("syntax-prefetch.service"));
+    assert!(service.contains(&format!("\"{}\"", bootstrapper_binary().display())));
+    assert!(service.contains(&format!("\"--install-dir\" \"{}\" \"--headless\" \"prefetch\"", environment.install_dir.display())));
+    assert!(read(&unit_directory.join("syntax-prefetch.timer")).contains("OnUnitActiveSec=3h"));
+    assert!(read(&systemctl_log).contains("--user enable --now syntax-prefetch.timer"));
+
+    let output = environment.command(&["prefetch", "--remove-schedule"]).env("PATH", &bin_directory).output().unwrap();
+    assert_success(&output);
+    assert!(!unit_directory.join("syntax-prefetch.timer").exists());
+    assert!(read(&systemctl_log).contains("--user disable --now syntax-prefetch.timer"));
+}
+
+#[test]
+fn install_schedule_escapes_systemd_specifiers() {
+    let environment = TestEnvironment::new(Vec::new());
+    let install_dir = environment.root.path().join("Syntax 100%h");
+    std::fs::create_dir_all(&install_dir).unwrap();
+    let (bin_directory, _) = fake_systemctl(&environment);
+
+    let output = environment.command(&["--install-dir", &install_dir.display().to_string(), "prefetch", "--install-schedule"])
+        .env("PATH", &bin_directory)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let service = read(&environment.config_dir.join("systemd").join("user").join("syntax-prefetch.service"));
+    assert!(service.contains(&format!("\"--install-dir\" \"{}\"", install_dir.display().to_string().replace('%', "%%"))), "{}", service);
+}